@@ -3,9 +3,10 @@
 /// - <https://github.com/kujirahand/nadesiko3rust>
 
 mod cli_function;
+mod repl;
 
 use std::fs;
-use nadesiko3::{node::NodeContext, runner, node, parser, token, tokenizer, sys_function};
+use nadesiko3::{node::NodeContext, runner, node, parser, token, tokenizer, indent, sys_function};
 
 fn main() {
     let mut src = String::from("");
@@ -22,6 +23,7 @@ fn main() {
             if arg.eq("-d") { debug_mode = true; }
             if arg.eq("-e") { eval_mode = true; }
             if arg.eq("-p") { parse_mode = true; }
+            if arg.eq("-h") || arg.eq("--help") { show_usage(); return; }
             continue;
         }
         // [memo] cargo run でもevalモードが使えるように「-」なしのモード
@@ -37,9 +39,9 @@ fn main() {
             continue;
         }
     }
-    // 何も指定がなかったとき
+    // 何も指定がなかったときは対話モード
     if filename == None && src.eq("") {
-        show_usage(); return;
+        repl::run(); return;
     }
     if debug_mode { println!("=== DEBUG mode({}) ===", runtime); }
     let filename = match filename {
@@ -76,7 +78,11 @@ fn include_file(context: &mut NodeContext, tokens: Vec<token::Token>) -> Vec<tok
             };
             // トークンに変換
             let fileno = context.set_filename(f);
-            let mut included_tokens = tokenizer::tokenize(&src, 0, fileno);
+            let included_tokens = tokenizer::tokenize(&src, 0, fileno);
+            let mut included_tokens = match indent::convert(&src, included_tokens, f) {
+                Ok(tokens) => tokens,
+                Err(e) => { println!("!!{}", e); return vec![]; },
+            };
             included_tokens.extend(tokens);
             tokens = included_tokens;
         }
@@ -95,6 +101,10 @@ fn compile_and_run(src: &str, fname: &str, debug_mode: bool, parse_mode: bool) {
     // tokenizer
     if debug_mode { println!("--- tokenize ---"); }
     let tokens = tokenizer::tokenize(src, 0, fileno);
+    let tokens = match indent::convert(src, tokens, fname) {
+        Ok(tokens) => tokens,
+        Err(e) => { println!("!!{}", e); return },
+    };
     if debug_mode { println!("{}", token::tokens_string(&tokens)); }
 
     if debug_mode { println!("--- include ---"); }
@@ -145,13 +155,15 @@ fn compile_and_run(src: &str, fname: &str, debug_mode: bool, parse_mode: bool) {
 
 fn show_usage() {
     println!(
-        "{}\n{}\n{}\n{}\n{}\n{}",
+        "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
         "[nadesiko3rust]",
         "[使い方] > nadesiko3 (options) (filename)",
+        "  (filename)を省略すると対話モードで起動します",
         "options:",
         "  -e, e, eval  ... ソースを直接指定して実行",
         "  -d, d, debug ... デバッグ情報を表示",
         "  -p, p, parse ... パースだけして表示",
+        "  -h, --help   ... 使い方を表示",
     );
 }
 
@@ -0,0 +1,297 @@
+//! 対話モード(REPL) ... 一行ずつプログラムを入力して実行する
+
+use std::fs;
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
+use nadesiko3::{josi_list, sys_function, tokenizer};
+use nadesiko3::node::{Node, NodeContext, NodeKind, NodeValue, SysArg};
+use nadesiko3::session::Session;
+use nadesiko3::token::{Token, TokenKind};
+use crate::cli_function;
+
+const HISTORY_FILE: &str = ".rnako3_history";
+
+/// 開いているブロックの種類
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Block {
+    If,
+    Switch,
+    Case,
+    Try,
+    Other,
+}
+
+/// メタコマンドの実行結果
+enum Command {
+    Continue,
+    Quit,
+}
+
+/// 対話モードを開始する
+pub fn run() {
+    let mut rl = match DefaultEditor::new() {
+        Ok(rl) => rl,
+        Err(e) => return println!("!!{}", e),
+    };
+    let history = history_path();
+    if let Some(path) = &history {
+        let _ = rl.load_history(path);
+    }
+    println!("[nadesiko3rust] 対話モード (:help で使い方を表示、:quit で終了)");
+    let mut session = new_session();
+    let mut code = String::new();
+    loop {
+        let prompt = if code.is_empty() { "> " } else { ". " };
+        let line = match rl.readline(prompt) {
+            Ok(line) => line,
+            // Ctrl+C は入力中のブロックを取り消す
+            Err(ReadlineError::Interrupted) => { code.clear(); continue; },
+            Err(ReadlineError::Eof) => break,
+            Err(e) => { println!("!!{}", e); break; },
+        };
+        if code.is_empty() && line.trim().is_empty() { continue; }
+        let _ = rl.add_history_entry(line.as_str());
+        // メタコマンド
+        if code.is_empty() {
+            if let Some(cmd) = read_command(&line) {
+                match run_command(&mut session, cmd) {
+                    Command::Continue => continue,
+                    Command::Quit => break,
+                }
+            }
+        }
+        code.push_str(&line);
+        code.push('\n');
+        // ブロックが閉じるまで続きを読む
+        if count_open_blocks(&code) > 0 { continue; }
+        eval_print(&mut session, &code);
+        code.clear();
+    }
+    if let Some(path) = &history {
+        let _ = rl.save_history(path);
+    }
+}
+
+/// 標準関数とコマンドライン用の関数を登録したセッションを作る
+fn new_session() -> Session {
+    let mut context = NodeContext::new();
+    context.set_filename("repl");
+    sys_function::register(&mut context);
+    cli_function::register(&mut context);
+    Session::from_context(context)
+}
+
+/// 履歴ファイルのパス (ホームディレクトリが分からなければ保存しない)
+fn history_path() -> Option<String> {
+    let home = std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")).ok()?;
+    let path = std::path::Path::new(&home).join(HISTORY_FILE);
+    Some(path.to_string_lossy().to_string())
+}
+
+/// 『:』で始まる行であればメタコマンドを返す
+fn read_command(line: &str) -> Option<&str> {
+    let line = line.trim();
+    line.strip_prefix(':').or_else(|| line.strip_prefix('：'))
+}
+
+fn run_command(session: &mut Session, cmd: &str) -> Command {
+    let (name, arg) = match cmd.split_once(char::is_whitespace) {
+        Some((name, arg)) => (name, arg.trim()),
+        None => (cmd, ""),
+    };
+    match name {
+        "q" | "quit" | "exit" => return Command::Quit,
+        "h" | "help" => show_help(),
+        "vars" => {
+            let vars = session.global_vars();
+            if vars.is_empty() { println!("(変数はありません)"); }
+            for (name, v) in vars.iter() {
+                println!("{} = {}", name, v.to_string());
+            }
+        },
+        "funcs" => {
+            let funcs = session.user_funcs();
+            if funcs.is_empty() { println!("(関数はありません)"); }
+            for (name, args) in funcs.iter() {
+                println!("{}", func_to_string(name, args));
+            }
+        },
+        "load" => {
+            if arg.is_empty() { println!("!!読み込むファイル名を指定してください。"); return Command::Continue; }
+            match fs::read_to_string(arg) {
+                Ok(src) => match session.eval(&src) {
+                    Ok(_) => println!("『{}』を読み込みました。", arg),
+                    Err(e) => println!("!!{}", e),
+                },
+                Err(err) => println!("ソースファイル『{}』が読めません。{}", arg, err),
+            }
+        },
+        "reset" => {
+            *session = new_session();
+            println!("セッションを初期化しました。");
+        },
+        _ => println!("!!『:{}』は不明なコマンドです。:help で使い方を表示します。", name),
+    }
+    Command::Continue
+}
+
+fn show_help() {
+    println!("[対話モードの使い方] プログラムを入力すると実行して、文ごとの値を表示します。
+  :vars        ... 変数の一覧を表示
+  :funcs       ... 関数の一覧を表示
+  :load (file) ... ファイルを読み込んで実行
+  :reset       ... 変数と関数の定義を消去
+  :help        ... 使い方を表示
+  :quit        ... 終了 (Ctrl+D)");
+}
+
+/// 『●(AとBを)加算』の形式で関数を表す
+fn func_to_string(name: &str, args: &[SysArg]) -> String {
+    if args.is_empty() { return format!("●{}", name); }
+    let args: Vec<String> = args.iter()
+        .map(|a| format!("{}{}", a.name, a.josi_list.first().map(|s| s.as_str()).unwrap_or("")))
+        .collect();
+    format!("●({}){}", args.join(""), name)
+}
+
+/// プログラムを実行して、文ごとに値を表示する
+fn eval_print(session: &mut Session, code: &str) {
+    session.clear_print_log();
+    let nodes = match session.parse(code) {
+        Ok(nodes) => nodes,
+        Err(e) => return println!("!!{}", e),
+    };
+    for node in nodes.iter() {
+        let log_len = session.get_print_log().len();
+        match session.run(std::slice::from_ref(node)) {
+            Ok(v) => {
+                // 表示した文や値を持たない文は表示しない
+                let printed = session.get_print_log().len() != log_len;
+                if has_value(node) && !printed && !matches!(v, NodeValue::Empty) {
+                    println!("{}", v.to_string());
+                }
+            },
+            Err(e) => return println!("!!{}", e),
+        }
+    }
+}
+
+/// 値を表示する文か (代入や制御構文は表示しない)
+fn has_value(node: &Node) -> bool {
+    !matches!(node.kind,
+        NodeKind::Nop | NodeKind::Comment | NodeKind::LetVarGlobal | NodeKind::LetVarLocal |
+        NodeKind::ArrayLet | NodeKind::If | NodeKind::Kai | NodeKind::While | NodeKind::Foreach |
+        NodeKind::Switch | NodeKind::Try | NodeKind::Break | NodeKind::Continue |
+        NodeKind::Return | NodeKind::For)
+}
+
+/// 『ここまで』で閉じていないブロックの数を数える
+pub fn count_open_blocks(src: &str) -> usize {
+    let tokens = tokenizer::tokenize(src, 0, 0);
+    let mut stack: Vec<Block> = vec![];
+    for line in tokens.split(|t| t.kind == TokenKind::Eol) {
+        let line: Vec<&Token> = line.iter()
+            .filter(|t| !matches!(t.kind, TokenKind::Comment | TokenKind::Comma))
+            .collect();
+        let (first, last) = match (line.first(), line.last()) {
+            (Some(first), Some(last)) => (first.kind, *last),
+            _ => continue,
+        };
+        let has_if = line.iter().any(|t| t.kind == TokenKind::If);
+        // 『ここから』と『ここまで』
+        for (i, t) in line.iter().enumerate() {
+            match t.kind {
+                TokenKind::BlockEnd => { stack.pop(); },
+                // 無名関数『関数(引数)』は『ここまで』で閉じる (『関数 (関数名)』は関数の値)
+                TokenKind::Func if line.get(i + 1).is_none_or(|next| next.kind != TokenKind::Word) => stack.push(Block::Other),
+                // 『違えばここから』は『もし』のブロックの続き
+                TokenKind::BlockBegin if first == TokenKind::Else && stack.last() == Some(&Block::If) => {},
+                TokenKind::BlockBegin => stack.push(if has_if { Block::If } else { Block::Other }),
+                _ => {},
+            }
+        }
+        if matches!(last.kind, TokenKind::BlockBegin | TokenKind::BlockEnd) { continue; }
+        let top = stack.last().copied();
+        let is_mosi = last.josi.as_deref().and_then(josi_list::is_josi_mosi).is_some();
+        match first {
+            // 関数の定義は必ず『ここまで』で閉じる (型の定義『●人とは型 (名前)』は一行で終わる)
+            TokenKind::DefFunc => {
+                if !is_def_type(&line) { stack.push(Block::Other); }
+                continue;
+            },
+            TokenKind::Else => {
+                if last.kind == TokenKind::Else {
+                    // 条件分岐の『違えば』はブロックを開く
+                    if top == Some(Block::Switch) { stack.push(Block::Case); }
+                } else if !is_mosi && top == Some(Block::If) {
+                    // 『違えば、(単文)』で『もし』のブロックが終わる
+                    stack.pop();
+                }
+                continue;
+            },
+            TokenKind::Catch => {
+                if last.kind != TokenKind::Catch && top == Some(Block::Try) { stack.pop(); }
+                continue;
+            },
+            _ => {},
+        }
+        if is_mosi {
+            stack.push(if has_if { Block::If } else { Block::Case });
+            continue;
+        }
+        match last.kind {
+            TokenKind::For | TokenKind::Kai | TokenKind::While | TokenKind::Foreach => stack.push(Block::Other),
+            TokenKind::Switch => stack.push(Block::Switch),
+            TokenKind::Try => stack.push(Block::Try),
+            _ => {},
+        }
+    }
+    stack.len()
+}
+
+/// 『●(型名)とは型 (フィールド, ...)』の行か
+fn is_def_type(line: &[&Token]) -> bool {
+    let is_type = line.windows(2).any(|w| w[0].kind == TokenKind::Word && w[0].value.eq_str("型") && w[1].kind == TokenKind::ParenL);
+    is_type && line.last().is_some_and(|t| t.kind == TokenKind::ParenR)
+}
+
+#[cfg(test)]
+mod test_repl {
+    use super::*;
+
+    #[test]
+    fn test_count_open_blocks() {
+        assert_eq!(count_open_blocks("「あ」を表示"), 0);
+        assert_eq!(count_open_blocks("もしAが1ならば"), 1);
+        assert_eq!(count_open_blocks("もしAが1ならば「あ」を表示"), 0);
+        assert_eq!(count_open_blocks("もしAが1ならば\n「あ」を表示\n違えば\n「い」を表示"), 1);
+        assert_eq!(count_open_blocks("もしAが1ならば\n「あ」を表示\n違えば\n「い」を表示\nここまで"), 0);
+        assert_eq!(count_open_blocks("もしAが1ならば\n「あ」を表示\n違えば、「い」を表示"), 0);
+        assert_eq!(count_open_blocks("●(AとBを)加算とは"), 1);
+        assert_eq!(count_open_blocks("●(AとBを)加算とは\nA+Bで戻る\nここまで"), 0);
+        assert_eq!(count_open_blocks("●人とは型 (名前, 年齢)"), 0);
+        assert_eq!(count_open_blocks("●人とは型 (名前)\n●人の挨拶とは"), 1);
+        assert_eq!(count_open_blocks("F=関数(A)"), 1);
+        assert_eq!(count_open_blocks("F=関数(A)\nA*2で戻る\nここまで"), 0);
+        assert_eq!(count_open_blocks("F=関数 加算"), 0);
+        assert_eq!(count_open_blocks("3回"), 1);
+        assert_eq!(count_open_blocks("3回、「あ」を表示"), 0);
+        assert_eq!(count_open_blocks("Iを1から3まで繰り返す\nもしI=2ならば\n「あ」を表示"), 2);
+        assert_eq!(count_open_blocks("ここから"), 1);
+        assert_eq!(count_open_blocks("Aで条件分岐\n1ならば\n「一」と表示\nここまで\n違えば"), 2);
+        assert_eq!(count_open_blocks("エラー監視\n「A」と表示\nエラーならば"), 1);
+        assert_eq!(count_open_blocks("エラー監視\n「A」と表示\nエラーならば、「B」と表示"), 0);
+    }
+    #[test]
+    fn test_eval() {
+        let mut s = new_session();
+        assert!(matches!(run_command(&mut s, "funcs"), Command::Continue));
+        s.eval("●(AとBを)加算とは\nA+Bで戻る\nここまで").unwrap();
+        assert_eq!(func_to_string("加算", &s.user_funcs()[0].1), "●(AとBを)加算");
+        assert_eq!(read_command("：vars"), Some("vars"));
+        assert_eq!(read_command("A=1"), None);
+        assert!(matches!(run_command(&mut s, "quit"), Command::Quit));
+        run_command(&mut s, "reset");
+        assert!(s.user_funcs().is_empty());
+    }
+}
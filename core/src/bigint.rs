@@ -0,0 +1,312 @@
+//! 任意精度の整数 (整数の計算が桁あふれした時に使う)
+
+use std::cmp::Ordering;
+
+/// 任意精度の整数 (符号と絶対値。絶対値は基数2^32で下位の桁から並べる)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+    neg: bool,
+    mag: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        Self { neg: false, mag: vec![] }
+    }
+    pub fn from_i128(v: i128) -> Self {
+        let mut n = v.unsigned_abs();
+        let mut mag = vec![];
+        while n > 0 {
+            mag.push(n as u32);
+            n >>= 32;
+        }
+        Self { neg: v < 0, mag }
+    }
+    pub fn from_isize(v: isize) -> Self {
+        Self::from_i128(v as i128)
+    }
+    fn from_mag(neg: bool, mag: Vec<u32>) -> Self {
+        let mut b = Self { neg, mag };
+        b.trim();
+        b
+    }
+    /// 上位の0を取り除く (0なら符号を正にする)
+    fn trim(&mut self) {
+        while self.mag.last() == Some(&0) {
+            self.mag.pop();
+        }
+        if self.mag.is_empty() { self.neg = false; }
+    }
+    pub fn is_zero(&self) -> bool {
+        self.mag.is_empty()
+    }
+    pub fn is_negative(&self) -> bool {
+        self.neg
+    }
+    /// 絶対値のビット数
+    pub fn bits(&self) -> usize {
+        match self.mag.last() {
+            Some(top) => (self.mag.len() - 1) * 32 + (32 - top.leading_zeros() as usize),
+            None => 0,
+        }
+    }
+    /// isizeの範囲に収まればisizeを返す
+    pub fn to_isize(&self) -> Option<isize> {
+        if self.mag.len() > 4 { return None; }
+        let mut n: u128 = 0;
+        for w in self.mag.iter().rev() {
+            n = (n << 32) | *w as u128;
+        }
+        let v: i128 = if self.neg {
+            if n > i128::MAX as u128 + 1 { return None; }
+            (n as i128).wrapping_neg()
+        } else {
+            i128::try_from(n).ok()?
+        };
+        isize::try_from(v).ok()
+    }
+    pub fn to_f64(&self) -> f64 {
+        let mut v = 0.0;
+        for w in self.mag.iter().rev() {
+            v = v * 4294967296.0 + *w as f64;
+        }
+        if self.neg { -v } else { v }
+    }
+    /// 10進数の文字列を読む
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::parse_radix(s, 10)
+    }
+    /// radix進数の文字列を読む (radixは2から36)
+    pub fn parse_radix(s: &str, radix: u32) -> Option<Self> {
+        let (neg, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        if digits.is_empty() { return None; }
+        let mut mag: Vec<u32> = vec![];
+        for c in digits.chars() {
+            mul_small_add(&mut mag, radix, c.to_digit(radix)?);
+        }
+        Some(Self::from_mag(neg, mag))
+    }
+
+    pub fn neg(&self) -> Self {
+        Self::from_mag(!self.neg, self.mag.clone())
+    }
+    pub fn add(&self, other: &Self) -> Self {
+        if self.neg == other.neg {
+            return Self::from_mag(self.neg, add_mag(&self.mag, &other.mag));
+        }
+        // 符号が異なれば絶対値の大きい方から引く
+        match cmp_mag(&self.mag, &other.mag) {
+            Ordering::Less => Self::from_mag(other.neg, sub_mag(&other.mag, &self.mag)),
+            _ => Self::from_mag(self.neg, sub_mag(&self.mag, &other.mag)),
+        }
+    }
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+    pub fn mul(&self, other: &Self) -> Self {
+        Self::from_mag(self.neg != other.neg, mul_mag(&self.mag, &other.mag))
+    }
+    /// 商と余りを返す (0に向かって切り捨て。余りの符号は割られる数と同じ。0で割るとNone)
+    pub fn div_rem(&self, other: &Self) -> Option<(Self, Self)> {
+        if other.is_zero() { return None; }
+        let (q, r) = div_rem_mag(&self.mag, &other.mag);
+        Some((Self::from_mag(self.neg != other.neg, q), Self::from_mag(self.neg, r)))
+    }
+    pub fn pow(&self, exp: u32) -> Self {
+        let mut result = Self::from_i128(1);
+        let mut base = self.clone();
+        let mut e = exp;
+        while e > 0 {
+            if e & 1 == 1 { result = result.mul(&base); }
+            e >>= 1;
+            if e > 0 { base = base.mul(&base); }
+        }
+        result
+    }
+    /// 16進数の文字列 (大文字)
+    pub fn to_hex_string(&self) -> String {
+        self.to_pow2_string(|w| format!("{:X}", w), |w| format!("{:08X}", w))
+    }
+    /// 2進数の文字列
+    pub fn to_bin_string(&self) -> String {
+        self.to_pow2_string(|w| format!("{:b}", w), |w| format!("{:032b}", w))
+    }
+    fn to_pow2_string(&self, top: fn(u32) -> String, rest: fn(u32) -> String) -> String {
+        let mut s = String::from(if self.neg { "-" } else { "" });
+        match self.mag.last() {
+            Some(w) => s.push_str(&top(*w)),
+            None => return String::from("0"),
+        }
+        for w in self.mag.iter().rev().skip(1) {
+            s.push_str(&rest(*w));
+        }
+        s
+    }
+}
+
+impl std::fmt::Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() { return write!(f, "0"); }
+        // 10^9ずつ割って下位の桁から得る
+        let mut chunks = vec![];
+        let mut mag = self.mag.clone();
+        while !mag.is_empty() {
+            let (q, r) = div_small(&mag, 1_000_000_000);
+            chunks.push(r);
+            mag = q;
+        }
+        let mut s = String::from(if self.neg { "-" } else { "" });
+        s.push_str(&chunks.pop().unwrap_or(0).to_string());
+        for c in chunks.iter().rev() {
+            s.push_str(&format!("{:09}", c));
+        }
+        write!(f, "{}", s)
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.neg, other.neg) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_mag(&self.mag, &other.mag),
+            (true, true) => cmp_mag(&other.mag, &self.mag),
+        }
+    }
+}
+
+fn cmp_mag(a: &[u32], b: &[u32]) -> Ordering {
+    if a.len() != b.len() { return a.len().cmp(&b.len()); }
+    for (x, y) in a.iter().rev().zip(b.iter().rev()) {
+        if x != y { return x.cmp(y); }
+    }
+    Ordering::Equal
+}
+
+fn add_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u64;
+    for i in 0..a.len().max(b.len()) {
+        let sum = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry > 0 { result.push(carry as u32); }
+    result
+}
+
+/// a - b (a >= b であること)
+fn sub_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, x) in a.iter().enumerate() {
+        let mut diff = *x as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = if diff < 0 { diff += 1 << 32; 1 } else { 0 };
+        result.push(diff as u32);
+    }
+    result
+}
+
+fn mul_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() { return vec![]; }
+    let mut result = vec![0u32; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, y) in b.iter().enumerate() {
+            let t = *x as u64 * *y as u64 + result[i + j] as u64 + carry;
+            result[i + j] = t as u32;
+            carry = t >> 32;
+        }
+        result[i + b.len()] = carry as u32;
+    }
+    result
+}
+
+/// mag = mag * m + add
+fn mul_small_add(mag: &mut Vec<u32>, m: u32, add: u32) {
+    let mut carry = add as u64;
+    for w in mag.iter_mut() {
+        let t = *w as u64 * m as u64 + carry;
+        *w = t as u32;
+        carry = t >> 32;
+    }
+    if carry > 0 { mag.push(carry as u32); }
+}
+
+fn div_small(a: &[u32], d: u32) -> (Vec<u32>, u32) {
+    let mut q = vec![0u32; a.len()];
+    let mut r = 0u64;
+    for i in (0..a.len()).rev() {
+        let cur = (r << 32) | a[i] as u64;
+        q[i] = (cur / d as u64) as u32;
+        r = cur % d as u64;
+    }
+    while q.last() == Some(&0) { q.pop(); }
+    (q, r as u32)
+}
+
+/// 筆算(2進数)で割り算をする
+fn div_rem_mag(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_mag(a, b) == Ordering::Less { return (vec![], a.to_vec()); }
+    if b.len() == 1 {
+        let (q, r) = div_small(a, b[0]);
+        return (q, if r == 0 { vec![] } else { vec![r] });
+    }
+    let mut q = vec![0u32; a.len()];
+    let mut r: Vec<u32> = vec![];
+    for i in (0..a.len() * 32).rev() {
+        // r = r * 2 + (aのiビット目)
+        mul_small_add(&mut r, 2, (a[i / 32] >> (i % 32)) & 1);
+        if cmp_mag(&r, b) != Ordering::Less {
+            r = sub_mag(&r, b);
+            while r.last() == Some(&0) { r.pop(); }
+            q[i / 32] |= 1 << (i % 32);
+        }
+    }
+    while q.last() == Some(&0) { q.pop(); }
+    (q, r)
+}
+
+#[cfg(test)]
+mod test_bigint {
+    use super::*;
+
+    #[test]
+    fn test_calc() {
+        let a = BigInt::parse("123456789012345678901234567890").unwrap();
+        let b = BigInt::parse("987654321098765432109876543210").unwrap();
+        assert_eq!(a.add(&b).to_string(), "1111111110111111111011111111100");
+        assert_eq!(a.sub(&b).to_string(), "-864197532086419753208641975320");
+        assert_eq!(a.mul(&b).to_string(), "121932631137021795226185032733622923332237463801111263526900");
+        let (q, r) = b.div_rem(&a).unwrap();
+        assert_eq!((q.to_string(), r.to_string()), (String::from("8"), String::from("9000000000900000000090")));
+        let (q, r) = a.neg().div_rem(&BigInt::from_isize(7)).unwrap();
+        assert_eq!((q.to_string(), r.to_string()), (String::from("-17636684144620811271604938270"), String::from("0")));
+        assert_eq!(BigInt::from_isize(2).pow(100).to_string(), "1267650600228229401496703205376");
+        assert!(a.div_rem(&BigInt::zero()).is_none());
+    }
+    #[test]
+    fn test_convert() {
+        let max = BigInt::from_isize(isize::MAX);
+        assert_eq!(max.to_isize(), Some(isize::MAX));
+        assert_eq!(max.add(&BigInt::from_isize(1)).to_isize(), None);
+        assert_eq!(BigInt::from_isize(isize::MIN).to_isize(), Some(isize::MIN));
+        assert_eq!(BigInt::from_isize(-255).to_hex_string(), "-FF");
+        assert_eq!(BigInt::from_isize(2).pow(64).to_hex_string(), "10000000000000000");
+        assert_eq!(BigInt::from_isize(5).to_bin_string(), "101");
+        assert_eq!(BigInt::zero().to_string(), "0");
+        assert!(BigInt::parse("-3") < BigInt::parse("2"));
+        assert!(BigInt::parse("x").is_none());
+        assert_eq!(BigInt::parse_radix("FFFFFFFFFFFFFFFFFF", 16).unwrap().to_hex_string(), "FFFFFFFFFFFFFFFFFF");
+        assert_eq!(BigInt::parse_radix("-101", 2).unwrap().to_string(), "-5");
+        assert!(BigInt::parse_radix("12", 2).is_none());
+    }
+}
@@ -0,0 +1,162 @@
+//! 10進数の小数 (金額など誤差なく計算したい値に使う)
+
+use std::cmp::Ordering;
+
+/// 割り算で求める小数点以下の桁数
+const DIV_SCALE: u32 = 20;
+
+/// 10進数の小数 (value / 10^scale を表す)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decimal {
+    value: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    /// value / 10^scale の値を作る (小数点以下の末尾の0は取り除く)
+    pub fn new(value: i128, scale: u32) -> Self {
+        let mut d = Self { value, scale };
+        while d.scale > 0 && d.value % 10 == 0 {
+            d.value /= 10;
+            d.scale -= 1;
+        }
+        d
+    }
+    pub fn from_isize(v: isize) -> Self {
+        Self::new(v as i128, 0)
+    }
+    /// 小数から作る (表示した時と同じ桁で変換する)
+    pub fn from_f64(v: f64) -> Option<Self> {
+        if !v.is_finite() { return None; }
+        Self::parse(&format!("{}", v))
+    }
+    /// 「-12.34」のような10進数の文字列を読む
+    pub fn parse(s: &str) -> Option<Self> {
+        let (neg, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (int_s, frac_s) = match digits.split_once('.') {
+            Some((i, f)) => (i, f),
+            None => (digits, ""),
+        };
+        if int_s.is_empty() && frac_s.is_empty() { return None; }
+        let mut value: i128 = 0;
+        for c in int_s.chars().chain(frac_s.chars()) {
+            let n = c.to_digit(10)? as i128;
+            value = value.checked_mul(10)?.checked_add(n)?;
+        }
+        let scale = u32::try_from(frac_s.len()).ok()?;
+        Some(Self::new(if neg { -value } else { value }, scale))
+    }
+    pub fn to_f64(&self) -> f64 {
+        self.value as f64 / 10f64.powi(self.scale as i32)
+    }
+    /// 整数部分 (0に向かって切り捨て)
+    pub fn to_isize(&self) -> isize {
+        let v = match pow10(self.scale) {
+            Some(p) => self.value / p,
+            None => 0,
+        };
+        isize::try_from(v).unwrap_or(if v < 0 { isize::MIN } else { isize::MAX })
+    }
+    pub fn is_zero(&self) -> bool {
+        self.value == 0
+    }
+
+    /// 小数点以下の桁数を揃えた値を返す
+    fn align(&self, other: &Self) -> Option<(i128, i128, u32)> {
+        let scale = self.scale.max(other.scale);
+        let a = self.value.checked_mul(pow10(scale - self.scale)?)?;
+        let b = other.value.checked_mul(pow10(scale - other.scale)?)?;
+        Some((a, b, scale))
+    }
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        let (a, b, scale) = self.align(other)?;
+        Some(Self::new(a.checked_add(b)?, scale))
+    }
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        let (a, b, scale) = self.align(other)?;
+        Some(Self::new(a.checked_sub(b)?, scale))
+    }
+    pub fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(Self::new(self.value.checked_mul(other.value)?, self.scale + other.scale))
+    }
+    /// 割り算 (割り切れなければ小数点以下DIV_SCALE桁で四捨五入する)
+    pub fn checked_div(&self, other: &Self) -> Option<Self> {
+        if other.is_zero() { return None; }
+        let n = self.value.checked_mul(pow10(DIV_SCALE + other.scale)?)?;
+        let mut q = n / other.value;
+        let r = n % other.value;
+        if r.unsigned_abs().checked_mul(2)? >= other.value.unsigned_abs() {
+            q += if (n < 0) != (other.value < 0) { -1 } else { 1 };
+        }
+        Some(Self::new(q, DIV_SCALE + self.scale))
+    }
+    pub fn checked_rem(&self, other: &Self) -> Option<Self> {
+        let (a, b, scale) = self.align(other)?;
+        Some(Self::new(a.checked_rem(b)?, scale))
+    }
+}
+
+fn pow10(n: u32) -> Option<i128> {
+    10i128.checked_pow(n)
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let digits = self.value.unsigned_abs().to_string();
+        let sign = if self.value < 0 { "-" } else { "" };
+        let scale = self.scale as usize;
+        if scale == 0 { return write!(f, "{}{}", sign, digits); }
+        // 小数点の位置に合わせて先頭を0で埋める
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (int_s, frac_s) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, int_s, frac_s)
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.align(other) {
+            Some((a, b, _)) => a.cmp(&b),
+            None => self.to_f64().total_cmp(&other.to_f64()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_decimal {
+    use super::*;
+
+    fn dec(s: &str) -> Decimal {
+        Decimal::parse(s).unwrap()
+    }
+    #[test]
+    fn test_calc() {
+        assert_eq!(dec("0.1").checked_add(&dec("0.2")), Some(dec("0.3")));
+        assert_eq!(dec("1.10").checked_sub(&dec("2.2")).unwrap().to_string(), "-1.1");
+        assert_eq!(dec("1.5").checked_mul(&dec("0.2")).unwrap().to_string(), "0.3");
+        assert_eq!(dec("1").checked_div(&dec("8")).unwrap().to_string(), "0.125");
+        assert_eq!(dec("2").checked_div(&dec("3")).unwrap().to_string(), "0.66666666666666666667");
+        assert_eq!(dec("-2").checked_div(&dec("3")).unwrap().to_string(), "-0.66666666666666666667");
+        assert_eq!(dec("7.5").checked_rem(&dec("2")).unwrap().to_string(), "1.5");
+        assert!(dec("1").checked_div(&dec("0")).is_none());
+    }
+    #[test]
+    fn test_convert() {
+        assert_eq!(dec("0.05").to_string(), "0.05");
+        assert_eq!(dec("-0.5").to_string(), "-0.5");
+        assert_eq!(dec("12.340").to_string(), "12.34");
+        assert_eq!(Decimal::from_f64(0.1).unwrap().to_string(), "0.1");
+        assert_eq!(dec("-3.9").to_isize(), -3);
+        assert!(dec("1.05") > dec("1.0499"));
+        assert!(Decimal::parse("1.2.3").is_none());
+        assert!(Decimal::parse("").is_none());
+    }
+}
@@ -0,0 +1,361 @@
+//! DNCLモード ... 『!DNCLモード』があれば大学入試共通テスト用プログラム表記(DNCL)をなでしこの書き方に変換する
+//! 行の数は変えないので、エラーの行番号は元のプログラムと一致する
+
+use crate::josi_list;
+use crate::kanautils;
+
+/// ブロックを表す行頭の記号
+const BLOCK_CHARS: [char; 9] = [' ', '\t', '　', '｜', '|', '⎿', '└', '│', '┃'];
+/// DNCLの関数名と対応するなでしこの関数名 (いずれも引数は一つ)
+const FUNC_NAMES: [(&str, &str); 3] = [("要素数", "要素数"), ("整数", "INT"), ("二進", "二進")];
+
+/// 最初の(空行でない)行が『!DNCLモード』か (文字列の中の行は見ない)
+pub fn is_dncl(src: &str) -> bool {
+    pragma_line(src).is_some()
+}
+
+/// 『!DNCLモード』の行番号
+fn pragma_line(src: &str) -> Option<usize> {
+    let (no, line) = src.split('\n').enumerate().find(|(_, line)| !line.trim().is_empty())?;
+    if is_pragma(line) { Some(no) } else { None }
+}
+
+fn is_pragma(line: &str) -> bool {
+    let s: String = line.trim().chars().map(kanautils::to_half_ascii).collect();
+    s == "!DNCLモード"
+}
+
+/// DNCLのプログラムをなでしこのプログラムに変換する
+pub fn convert(src: &str) -> String {
+    let pragma = pragma_line(src);
+    let lines: Vec<String> = src.split('\n').enumerate().map(|(no, line)| {
+        let (line, cr) = match line.strip_suffix('\r') {
+            Some(l) => (l, "\r"),
+            None => (line, ""),
+        };
+        if Some(no) == pragma { return format!("#!DNCLモード{}", cr); }
+        format!("{}{}", convert_line(line), cr)
+    }).collect();
+    lines.join("\n")
+}
+
+/// 一行を変換する
+fn convert_line(line: &str) -> String {
+    let body = to_half_code(line.trim_start_matches(BLOCK_CHARS));
+    let body = body.trim_end();
+    // コメント
+    if body.starts_with('#') || body.starts_with('※') || body.starts_with("//") {
+        return String::from(body);
+    }
+    let body = body.trim_end_matches([',', '、']);
+    // もし文の終わり
+    if body == "を実行する" { return String::from("ここまで"); }
+    if let Some(rest) = body.strip_prefix("を実行し") {
+        let rest = rest.trim_start_matches([',', '、', ' ']);
+        if let Some(cond) = rest.strip_prefix("そうでなくもし") {
+            return format!("違えばもし{}", convert_expr(cond, false));
+        }
+        if rest.starts_with("そうでなければ") { return String::from("違えば"); }
+    }
+    // 繰り返しの終わり
+    if body.starts_with("を繰り返す") { return String::from("ここまで"); }
+    // 後判定の繰り返し『を繰り返し,(本文)を,(条件)になるまで実行する』
+    if body.starts_with("を繰り返し") { return String::from("永遠の間"); }
+    if let Some(cond) = body.strip_prefix('を').and_then(|s| s.strip_suffix("になるまで実行する")) {
+        let cond = cond.trim_start_matches([',', '、', ' ']).trim_end();
+        return format!("もし({})ならば抜ける;ここまで", convert_expr(cond, false));
+    }
+    // 『(変数)を(初期値)から(終値)まで(差分)ずつ増やしながら』
+    for w in ["ずつ増やしながら", "ずつ減らしながら"] {
+        if let Some(head) = body.strip_suffix(w) {
+            return format!("{}ずつ繰り返す", convert_expr(head.trim_end(), false));
+        }
+    }
+    // 『(値)と(値)を表示する』は文字列を連結して表示する
+    if let Some(head) = body.strip_suffix("を表示する") {
+        let items: Vec<String> = split_display_items(head).iter()
+            .map(|item| format!("({})", convert_expr(item.trim(), false)))
+            .collect();
+        return format!("{}を表示", items.join("&"));
+    }
+    // 代入文は『,』で区切って複数書ける
+    let is_let = body.contains('←');
+    convert_expr(body, is_let)
+}
+
+/// 文字列以外の全角記号を半角にする
+fn to_half_code(line: &str) -> String {
+    let mut result = String::new();
+    let mut end_of_str: Option<char> = None;
+    for c in line.chars() {
+        match end_of_str {
+            Some(e) => {
+                if c == e { end_of_str = None; }
+                result.push(c);
+            },
+            None => {
+                end_of_str = str_end(c);
+                result.push(if end_of_str.is_some() { c } else { kanautils::to_half_ascii(c) });
+            }
+        }
+    }
+    result
+}
+
+/// 文字列の開始記号であれば終了記号を返す
+fn str_end(c: char) -> Option<char> {
+    match c {
+        '「' => Some('」'),
+        '『' => Some('』'),
+        '"' => Some('"'),
+        _ => None,
+    }
+}
+
+/// 対応する閉じ括弧の位置を探す (文字列の中は無視する)
+fn find_close(chars: &[char], open_index: usize, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = open_index;
+    while i < chars.len() {
+        let c = chars[i];
+        if let Some(e) = str_end(c) {
+            i += chars[i + 1..].iter().position(|x| *x == e)? + 2;
+            continue;
+        }
+        if c == open { depth += 1; }
+        if c == close {
+            depth -= 1;
+            if depth == 0 { return Some(i); }
+        }
+        i += 1;
+    }
+    None
+}
+
+/// 括弧と文字列の外にある区切り文字で分割する
+fn split_top_level(chars: &[char], is_delimiter: impl Fn(usize) -> bool) -> Vec<String> {
+    let mut items = vec![];
+    let mut depth = 0;
+    let mut item = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if let Some(e) = str_end(c) {
+            let end = chars[i + 1..].iter().position(|x| *x == e).map(|p| i + p + 2).unwrap_or(chars.len());
+            item.extend(&chars[i..end]);
+            i = end;
+            continue;
+        }
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {},
+        }
+        if depth == 0 && is_delimiter(i) {
+            items.push(item);
+            item = String::new();
+        } else {
+            item.push(c);
+        }
+        i += 1;
+    }
+    items.push(item);
+    items
+}
+
+/// 『「答えは」と x を表示する』の表示する値を区切る
+fn split_display_items(head: &str) -> Vec<String> {
+    let chars: Vec<char> = head.chars().collect();
+    split_top_level(&chars, |i| {
+        if chars[i] != 'と' { return false; }
+        let prev = if i > 0 { chars[i - 1] } else { ' ' };
+        let next = chars.get(i + 1).copied().unwrap_or(' ');
+        matches!(prev, ' ' | '」' | '』' | '"' | ')' | ']') || matches!(next, ' ' | '「' | '『' | '"')
+    })
+}
+
+/// 割り算の項に使える文字か (ひらがなは助詞なので含めない)
+fn is_operand_char(c: char) -> bool {
+    c == '_' || c == '.' || (c.is_alphanumeric() && !kanautils::is_hiragana(c))
+}
+
+/// 項の始まりの位置を後ろから探す (x, A[i], 要素数(A), (a+b) など)
+fn operand_start(chars: &[char], end: usize) -> usize {
+    let mut i = end;
+    while i > 0 {
+        let c = chars[i - 1];
+        let open = match c {
+            ')' => '(',
+            ']' => '[',
+            _ if is_operand_char(c) => { i -= 1; continue; },
+            _ => break,
+        };
+        // 対応する開き括弧まで戻る
+        let mut depth = 0;
+        while i > 0 {
+            i -= 1;
+            if chars[i] == c { depth += 1; }
+            if chars[i] == open { depth -= 1; if depth == 0 { break; } }
+        }
+    }
+    i
+}
+
+/// 項の終わりの位置を探す
+fn operand_end(chars: &[char], start: usize) -> usize {
+    let mut i = start;
+    if chars.get(i) == Some(&'-') { i += 1; }
+    while i < chars.len() {
+        let c = chars[i];
+        let close = match c {
+            '(' => ')',
+            '[' => ']',
+            _ if is_operand_char(c) => { i += 1; continue; },
+            _ => break,
+        };
+        match find_close(chars, i, c, close) {
+            Some(close) => i = close + 1,
+            None => break,
+        }
+    }
+    i
+}
+
+/// 整数の商 a ÷ b → 整数((a)/(b)) (掛け算・割り算が続く場合は左から順に計算する)
+fn convert_int_div(chars: &[char]) -> Vec<char> {
+    let mut chars = chars.to_vec();
+    loop {
+        // 文字列の外の『÷』を探す
+        let mut pos = None;
+        let mut i = 0;
+        while i < chars.len() {
+            if let Some(e) = str_end(chars[i]) {
+                i = chars[i + 1..].iter().position(|x| *x == e).map(|p| i + p + 2).unwrap_or(chars.len());
+                continue;
+            }
+            if chars[i] == '÷' { pos = Some(i); break; }
+            i += 1;
+        }
+        let Some(pos) = pos else { return chars };
+        let skip_space_back = |mut j: usize| { while j > 0 && chars[j - 1] == ' ' { j -= 1; } j };
+        // 左の項 (a * b ÷ c なら a * b)
+        let left_end = skip_space_back(pos);
+        let mut left_start = operand_start(&chars, left_end);
+        loop {
+            let j = skip_space_back(left_start);
+            if j == 0 || !matches!(chars[j - 1], '*' | '×' | '/' | '%') { break; }
+            left_start = operand_start(&chars, skip_space_back(j - 1));
+        }
+        // 右の項
+        let mut right_start = pos + 1;
+        while chars.get(right_start) == Some(&' ') { right_start += 1; }
+        let right_end = operand_end(&chars, right_start);
+        let left: String = chars[left_start..left_end].iter().collect();
+        let right: String = chars[right_start..right_end].iter().collect();
+        let expr = format!("整数(({})/({}))", left, right);
+        chars.splice(left_start..right_end, expr.chars());
+    }
+}
+
+/// 式を変換する (split_comma がtrueなら『,』を文の区切りにする)
+fn convert_expr(expr: &str, split_comma: bool) -> String {
+    let chars: Vec<char> = expr.chars().collect();
+    if split_comma {
+        let parts = split_top_level(&chars, |i| chars[i] == ',');
+        if parts.len() > 1 {
+            let parts: Vec<String> = parts.iter().map(|p| convert_expr(p.trim(), false)).collect();
+            return parts.join(";");
+        }
+    }
+    let chars = convert_int_div(&chars);
+    let mut result = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        // 文字列はそのまま
+        if let Some(e) = str_end(c) {
+            let end = chars[i + 1..].iter().position(|x| *x == e).map(|p| i + p + 2).unwrap_or(chars.len());
+            result.extend(&chars[i..end]);
+            i = end;
+            continue;
+        }
+        // 助詞の前の空白を詰める (x を 1 から → xを 1から)
+        if c == ' ' {
+            let rest: String = chars[i..].iter().skip_while(|c| **c == ' ').collect();
+            if josi_list::JOSI_LIST.iter().any(|josi| rest.starts_with(josi)) { i += 1; continue; }
+        }
+        // 関数呼び出し 要素数(A) → ((A)の要素数)
+        let prev_is_word = result.chars().last().is_some_and(|p| p != ' ' && kanautils::is_word_chars(p));
+        if !prev_is_word {
+            if let Some((name, len, close)) = FUNC_NAMES.iter().find_map(|(dncl, name)| {
+                let len = dncl.chars().count();
+                if chars[i..].starts_with(&dncl.chars().collect::<Vec<char>>()) && chars.get(i + len) == Some(&'(') {
+                    find_close(&chars, i + len, '(', ')').map(|close| (name, len, close))
+                } else { None }
+            }) {
+                let arg: String = chars[i + len + 1..close].iter().collect();
+                result.push_str(&format!("(({})の{})", convert_expr(&arg, false), name));
+                i = close + 1;
+                continue;
+            }
+        }
+        match c {
+            '←' => result.push('='),
+            '【' if chars[i..].starts_with(&"【外部からの入力】".chars().collect::<Vec<char>>()) => {
+                result.push_str("(「」と尋ねる)");
+                i += "【外部からの入力】".chars().count();
+                continue;
+            },
+            // 配列の添字は1から始まる A[i] → A[(i)-1]
+            '[' if prev_is_word || result.ends_with(']') || result.ends_with(')') => {
+                let close = match find_close(&chars, i, '[', ']') {
+                    Some(close) => close,
+                    None => { result.push(c); i += 1; continue; }
+                };
+                let inner: Vec<char> = chars[i + 1..close].to_vec();
+                for index in split_top_level(&inner, |j| inner[j] == ',') {
+                    result.push_str(&format!("[({})-1]", convert_expr(index.trim(), false)));
+                }
+                i = close + 1;
+                continue;
+            },
+            // 配列の初期化 {1, 2, 3} → [1, 2, 3]
+            '{' => result.push('['),
+            '}' => result.push(']'),
+            _ => result.push(c),
+        }
+        i += 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod test_dncl {
+    use super::*;
+
+    #[test]
+    fn test_convert() {
+        assert!(is_dncl("!DNCLモード\nx ← 1"));
+        assert!(!is_dncl("x = 1"));
+        assert!(is_dncl("\n  \n!DNCLモード\nx ← 1"));
+        assert!(!is_dncl("S=「\n!DNCLモード\n」;A=[5,6];A[1]を表示"));
+        assert_eq!(convert_line("x ← x + 1"), "x = x + 1");
+        assert_eq!(convert_line("a ← 1, b ← 2"), "a = 1;b = 2");
+        assert_eq!(convert_line("Tokuten ← {87, 45, 72}"), "Tokuten = [87, 45, 72]");
+        assert_eq!(convert_line("｜ Tokuten[i] ← Tokuten[i + 1]"), "Tokuten[(i)-1] = Tokuten[(i + 1)-1]");
+        assert_eq!(convert_line("x ← 【外部からの入力】"), "x = (「」と尋ねる)");
+        assert_eq!(convert_line("もし x < 3 ならば"), "もし x < 3ならば");
+        assert_eq!(convert_line("を実行し，そうでなくもし x = 3 ならば"), "違えばもし x = 3ならば");
+        assert_eq!(convert_line("を実行し，そうでなければ"), "違えば");
+        assert_eq!(convert_line("を実行する"), "ここまで");
+        assert_eq!(convert_line("i を 1 から 要素数(Tokuten) まで 1 ずつ増やしながら，"), "iを 1から ((Tokuten)の要素数)まで 1ずつ繰り返す");
+        assert_eq!(convert_line("x < 10 の間，"), "x < 10の間");
+        assert_eq!(convert_line("を繰り返す"), "ここまで");
+        assert_eq!(convert_line("「合計は」と goukei と「点」を表示する"), "(「合計は」)&(goukei)&(「点」)を表示");
+        assert_eq!(convert_line("を，x ≧ 10 になるまで実行する"), "もし(x ≧ 10)ならば抜ける;ここまで");
+        // ÷ は整数の商
+        assert_eq!(convert_line("x ← 7 ÷ 2"), "x = (((7)/(2))のINT)");
+        assert_eq!(convert_line("x ← a + b * c ÷ (d - 1)"), "x = a + (((b * c)/((d - 1)))のINT)");
+        assert_eq!(convert_line("x ← A[i] ÷ 2 ÷ n"), "x = ((((((A[(i)-1])/(2))のINT))/(n))のINT)");
+    }
+}
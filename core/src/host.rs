@@ -0,0 +1,250 @@
+//! ホストアプリ(Rust)の値や関数をなでしこで使うための変換
+//! `NodeContext::add_func` で普通のRustの関数を引数の型を確かめて呼び出せるようにする
+
+use std::collections::HashMap;
+use crate::node::{NodeDict, NodeValue};
+
+/// なでしこの値からRustの値への変換
+pub trait FromNodeValue: Sized {
+    /// エラーメッセージで使う型の名前
+    fn type_name() -> String;
+    /// 変換できなければNoneを返す
+    fn from_node_value(v: &NodeValue) -> Option<Self>;
+}
+
+/// Rustの値からなでしこの値への変換
+pub trait IntoNodeValue {
+    fn into_node_value(self) -> NodeValue;
+}
+
+/// 参照(Link)であれば参照先の値に対してfを呼ぶ
+fn with_link<R>(v: &NodeValue, f: impl FnOnce(&NodeValue) -> R) -> R {
+    match v {
+        NodeValue::Link(link) => with_link(&link.borrow(), f),
+        _ => f(v),
+    }
+}
+
+impl FromNodeValue for NodeValue {
+    fn type_name() -> String { String::from("値") }
+    fn from_node_value(v: &NodeValue) -> Option<Self> {
+        Some(with_link(v, |v| v.clone()))
+    }
+}
+impl FromNodeValue for isize {
+    fn type_name() -> String { String::from("整数") }
+    fn from_node_value(v: &NodeValue) -> Option<Self> {
+        with_link(v, |v| match v {
+            NodeValue::I(n) => Some(*n),
+            NodeValue::BigInt(n) => n.to_isize(),
+            NodeValue::F(n) if n.fract() == 0.0 && n.abs() < isize::MAX as f64 => Some(*n as isize),
+            NodeValue::Decimal(n) if n.to_f64().fract() == 0.0 => Some(n.to_isize()),
+            NodeValue::S(s) => s.trim().parse().ok(),
+            _ => None,
+        })
+    }
+}
+impl FromNodeValue for i64 {
+    fn type_name() -> String { isize::type_name() }
+    fn from_node_value(v: &NodeValue) -> Option<Self> {
+        isize::from_node_value(v).and_then(|n| i64::try_from(n).ok())
+    }
+}
+impl FromNodeValue for f64 {
+    fn type_name() -> String { String::from("数値") }
+    fn from_node_value(v: &NodeValue) -> Option<Self> {
+        with_link(v, |v| match v {
+            NodeValue::I(n) => Some(*n as f64),
+            NodeValue::BigInt(n) => Some(n.to_f64()),
+            NodeValue::F(n) => Some(*n),
+            NodeValue::Decimal(n) => Some(n.to_f64()),
+            NodeValue::S(s) => s.trim().parse().ok(),
+            _ => None,
+        })
+    }
+}
+impl FromNodeValue for bool {
+    fn type_name() -> String { String::from("真偽値") }
+    fn from_node_value(v: &NodeValue) -> Option<Self> {
+        with_link(v, |v| match v {
+            NodeValue::B(_) | NodeValue::I(_) | NodeValue::BigInt(_) | NodeValue::F(_) | NodeValue::Decimal(_) => Some(v.to_bool()),
+            _ => None,
+        })
+    }
+}
+impl FromNodeValue for String {
+    fn type_name() -> String { String::from("文字列") }
+    fn from_node_value(v: &NodeValue) -> Option<Self> {
+        with_link(v, |v| match v {
+            NodeValue::Empty | NodeValue::S(_) | NodeValue::I(_) | NodeValue::BigInt(_) |
+            NodeValue::F(_) | NodeValue::Decimal(_) | NodeValue::B(_) => Some(v.to_string()),
+            _ => None,
+        })
+    }
+}
+impl<T: FromNodeValue> FromNodeValue for Vec<T> {
+    fn type_name() -> String { format!("{}の配列", T::type_name()) }
+    fn from_node_value(v: &NodeValue) -> Option<Self> {
+        with_link(v, |v| match v {
+            NodeValue::A(list) => list.iter().map(T::from_node_value).collect(),
+            _ => None,
+        })
+    }
+}
+/// 空の値はNoneにする
+impl<T: FromNodeValue> FromNodeValue for Option<T> {
+    fn type_name() -> String { T::type_name() }
+    fn from_node_value(v: &NodeValue) -> Option<Self> {
+        with_link(v, |v| match v {
+            NodeValue::Empty => Some(None),
+            _ => T::from_node_value(v).map(Some),
+        })
+    }
+}
+impl<T: FromNodeValue> FromNodeValue for HashMap<String, T> {
+    fn type_name() -> String { format!("{}の辞書", T::type_name()) }
+    fn from_node_value(v: &NodeValue) -> Option<Self> {
+        with_link(v, |v| match v {
+            NodeValue::H(dict) | NodeValue::R(_, dict) => dict.iter()
+                .map(|(k, v)| T::from_node_value(v).map(|v| (k.clone(), v)))
+                .collect(),
+            _ => None,
+        })
+    }
+}
+
+impl IntoNodeValue for NodeValue {
+    fn into_node_value(self) -> NodeValue { self }
+}
+impl IntoNodeValue for () {
+    fn into_node_value(self) -> NodeValue { NodeValue::Empty }
+}
+impl IntoNodeValue for isize {
+    fn into_node_value(self) -> NodeValue { NodeValue::I(self) }
+}
+impl IntoNodeValue for i64 {
+    fn into_node_value(self) -> NodeValue {
+        match isize::try_from(self) {
+            Ok(v) => NodeValue::I(v),
+            Err(_) => NodeValue::F(self as f64),
+        }
+    }
+}
+impl IntoNodeValue for f64 {
+    fn into_node_value(self) -> NodeValue { NodeValue::F(self) }
+}
+impl IntoNodeValue for bool {
+    fn into_node_value(self) -> NodeValue { NodeValue::B(self) }
+}
+impl IntoNodeValue for String {
+    fn into_node_value(self) -> NodeValue { NodeValue::S(self) }
+}
+impl IntoNodeValue for &str {
+    fn into_node_value(self) -> NodeValue { NodeValue::from_str(self) }
+}
+impl<T: IntoNodeValue> IntoNodeValue for Vec<T> {
+    fn into_node_value(self) -> NodeValue {
+        NodeValue::A(self.into_iter().map(T::into_node_value).collect())
+    }
+}
+/// Noneは空の値にする
+impl<T: IntoNodeValue> IntoNodeValue for Option<T> {
+    fn into_node_value(self) -> NodeValue {
+        match self {
+            Some(v) => v.into_node_value(),
+            None => NodeValue::Empty,
+        }
+    }
+}
+/// 順番が決まらないのでキーの順に並べる
+impl<T: IntoNodeValue> IntoNodeValue for HashMap<String, T> {
+    fn into_node_value(self) -> NodeValue {
+        let mut items: Vec<(String, T)> = self.into_iter().collect();
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut dict = NodeDict::new();
+        for (k, v) in items {
+            dict.set(&k, v.into_node_value());
+        }
+        NodeValue::H(dict)
+    }
+}
+
+/// 関数の引数(index番目)をRustの値に変換する
+pub fn get_arg<T: FromNodeValue>(name: &str, args: &[NodeValue], index: usize) -> Result<T, String> {
+    let v = match args.get(index) {
+        Some(v) => v,
+        None => return Err(format!("『{}』の引数が足りません。", name)),
+    };
+    match T::from_node_value(v) {
+        Some(v) => Ok(v),
+        None => Err(format!("『{}』の{}番目の引数には{}が必要ですが『{}』が指定されました。",
+            name, index + 1, T::type_name(), v.to_string())),
+    }
+}
+
+/// `NodeContext::add_func` で登録できるRustの関数 (Argsは引数の型の組)
+pub trait HostFunc<Args> {
+    /// 引数の数
+    fn args_len(&self) -> usize;
+    /// 引数を変換して呼び出す (変換できなければエラーメッセージを返す)
+    fn invoke(&self, name: &str, args: &[NodeValue]) -> Result<NodeValue, String>;
+}
+
+macro_rules! impl_host_func {
+    ($len:expr; $($t:ident $i:tt),*) => {
+        impl<F, R, $($t),*> HostFunc<($($t,)*)> for F
+        where F: Fn($($t),*) -> R, R: IntoNodeValue, $($t: FromNodeValue),* {
+            fn args_len(&self) -> usize { $len }
+            #[allow(unused_variables)]
+            fn invoke(&self, name: &str, args: &[NodeValue]) -> Result<NodeValue, String> {
+                Ok(self($(get_arg::<$t>(name, args, $i)?),*).into_node_value())
+            }
+        }
+    };
+}
+impl_host_func!(0;);
+impl_host_func!(1; A 0);
+impl_host_func!(2; A 0, B 1);
+impl_host_func!(3; A 0, B 1, C 2);
+impl_host_func!(4; A 0, B 1, C 2, D 3);
+impl_host_func!(5; A 0, B 1, C 2, D 3, E 4);
+impl_host_func!(6; A 0, B 1, C 2, D 3, E 4, G 5);
+
+#[cfg(test)]
+mod test_host {
+    use super::*;
+
+    #[test]
+    fn test_convert() {
+        assert_eq!(i64::from_node_value(&NodeValue::I(3)), Some(3));
+        assert_eq!(i64::from_node_value(&NodeValue::from_str("12")), Some(12));
+        assert_eq!(i64::from_node_value(&NodeValue::F(1.5)), None);
+        assert_eq!(f64::from_node_value(&NodeValue::I(2)), Some(2.0));
+        assert_eq!(String::from_node_value(&NodeValue::I(2)), Some(String::from("2")));
+        assert_eq!(bool::from_node_value(&NodeValue::from_str("あ")), None);
+        let list = NodeValue::A(vec![NodeValue::I(1), NodeValue::I(2)]);
+        assert_eq!(Vec::<i64>::from_node_value(&list), Some(vec![1, 2]));
+        assert_eq!(Vec::<bool>::from_node_value(&NodeValue::A(vec![NodeValue::from_str("x")])), None);
+        assert_eq!(Option::<i64>::from_node_value(&NodeValue::Empty), Some(None));
+        assert_eq!(vec![Some(1i64), None].into_node_value(), NodeValue::A(vec![NodeValue::I(1), NodeValue::Empty]));
+        let mut map = HashMap::new();
+        map.insert(String::from("b"), 2i64);
+        map.insert(String::from("a"), 1i64);
+        let dict = map.clone().into_node_value();
+        assert_eq!(dict.to_json_string(), "{\"a\":1,\"b\":2}");
+        assert_eq!(HashMap::<String, i64>::from_node_value(&dict), Some(map));
+        assert_eq!(Vec::<i64>::type_name(), "整数の配列");
+    }
+    #[test]
+    fn test_call() {
+        let add = |a: i64, b: i64| a + b;
+        assert_eq!(add.args_len(), 2);
+        let res = add.invoke("加算", &[NodeValue::I(1), NodeValue::I(2)]);
+        assert_eq!(res.map(|v| v.to_string()), Ok(String::from("3")));
+        let res = add.invoke("加算", &[NodeValue::I(1)]);
+        assert_eq!(res.map(|v| v.to_string()), Err(String::from("『加算』の引数が足りません。")));
+        let res = add.invoke("加算", &[NodeValue::I(1), NodeValue::from_str("あ")]);
+        assert_eq!(res.map(|v| v.to_string()),
+            Err(String::from("『加算』の2番目の引数には整数が必要ですが『あ』が指定されました。")));
+    }
+}
@@ -0,0 +1,173 @@
+//! インデント構文 ... 『!インデント構文』があれば字下げでブロックを表す
+//! 字下げが戻った所に『ここまで』を補う (ブロックの開始は従来通り行末の改行で判定する)
+
+use crate::token::*;
+use crate::josi_list;
+use crate::node::{NodeError, NodeErrorKind, NodeErrorLevel};
+
+/// トークン列の字下げを『ここまで』に変換する (『!インデント構文』がなければそのまま返す)
+pub fn convert(src: &str, tokens: Vec<Token>, filename: &str) -> Result<Vec<Token>, String> {
+    let (tokens, enabled) = read_pragma(tokens);
+    if !enabled { return Ok(tokens); }
+    let lines: Vec<Vec<char>> = src.split('\n').map(|line| line.chars().collect()).collect();
+    let mut result: Vec<Token> = vec![];
+    // (ブロック本文の字下げ幅, ブロックを開始した行の先頭トークン)
+    let mut levels: Vec<(usize, TokenKind)> = vec![(0, TokenKind::None)];
+    let mut last_row = 0;
+    let mut last_head = TokenKind::None;
+    // 括弧の深さ (括弧の中で改行した行の字下げは見ない)
+    let mut depth: usize = 0;
+    // 直前の行のトークン (ブロックを開く行か調べる)
+    let mut line: Vec<Token> = vec![];
+    for t in tokens.into_iter() {
+        // 字下げ構文では『ここまで』は不要なので読み飛ばす
+        if t.kind == TokenKind::BlockEnd {
+            result.push(Token::new_comment("ここまで", t.pos));
+            continue;
+        }
+        let in_bracket = depth > 0;
+        match t.kind {
+            TokenKind::ParenL | TokenKind::BracketL | TokenKind::CurBracketL => depth += 1,
+            TokenKind::ParenR | TokenKind::BracketR | TokenKind::CurBracketR => depth = depth.saturating_sub(1),
+            _ => {},
+        }
+        if in_bracket || t.kind == TokenKind::Eol || t.kind == TokenKind::Comment || t.pos.row == last_row {
+            if !matches!(t.kind, TokenKind::Eol | TokenKind::Comment) { line.push(t.clone()); }
+            result.push(t);
+            continue;
+        }
+        last_row = t.pos.row;
+        // 行頭のトークンであれば字下げ幅を調べる (複数行にわたる文字列の続きなどは対象外)
+        let indent = match count_indent(&lines, &t.pos) {
+            Some(n) => n,
+            None => { line.push(t.clone()); result.push(t); continue; }
+        };
+        let top = levels.last().map(|v| v.0).unwrap_or(0);
+        if indent > top {
+            // ブロックを開く行の後でなければ字下げできない
+            if !opens_block(&line) {
+                return Err(indent_error(&t, filename));
+            }
+            levels.push((indent, last_head));
+        }
+        line.clear();
+        line.push(t.clone());
+        while indent < levels.last().map(|v| v.0).unwrap_or(0) {
+            let (_, opener) = levels.pop().unwrap_or((0, TokenKind::None));
+            let cur_top = levels.last().map(|v| v.0).unwrap_or(0);
+            if indent > cur_top {
+                return Err(indent_error(&t, filename));
+            }
+            // 『もし』に続く『違えば』と『エラー監視』に続く『エラーならば』は同じ構文の続き
+            let is_continued = indent == cur_top && matches!(
+                (opener, t.kind),
+                (TokenKind::If | TokenKind::Else, TokenKind::Else) | (TokenKind::Try, TokenKind::Catch));
+            if !is_continued {
+                result.push(Token::new_str(TokenKind::BlockEnd, "ここまで", t.pos));
+            }
+        }
+        last_head = t.kind;
+        result.push(t);
+    }
+    // 末尾で開いているブロックを閉じる
+    let pos = result.last().map(|t| t.pos).unwrap_or(TokenPos::new(0, 0, 0));
+    while levels.len() > 1 {
+        levels.pop();
+        result.push(Token::new_str(TokenKind::BlockEnd, "ここまで", pos));
+    }
+    Ok(result)
+}
+
+fn indent_error(t: &Token, filename: &str) -> String {
+    let err = NodeError::new(
+        NodeErrorKind::ParserError, NodeErrorLevel::Error,
+        format!("『{}』の近くで、インデントが揃っていません。", t.value.to_string()),
+        t.pos.row, t.pos.col, String::from(filename));
+    err.to_string()
+}
+
+/// ブロックを開く行か (『もし..ならば』『繰り返す』『●..とは』『違えば』など行末で本文が続く行)
+fn opens_block(line: &[Token]) -> bool {
+    let (first, last) = match (line.first(), line.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return false,
+    };
+    if first.kind == TokenKind::DefFunc { return true; }
+    // 無名関数『関数(引数)』
+    let has_func_literal = line.iter().enumerate()
+        .any(|(i, t)| t.kind == TokenKind::Func && line.get(i + 1).is_none_or(|next| next.kind != TokenKind::Word));
+    if has_func_literal { return true; }
+    if last.josi.as_deref().and_then(josi_list::is_josi_mosi).is_some() { return true; }
+    matches!(last.kind,
+        TokenKind::For | TokenKind::Kai | TokenKind::While | TokenKind::Foreach | TokenKind::Switch |
+        TokenKind::Try | TokenKind::Catch | TokenKind::Else | TokenKind::BlockBegin)
+}
+
+/// 行頭の『!インデント構文』を探してコメントに置き換える
+fn read_pragma(mut tokens: Vec<Token>) -> (Vec<Token>, bool) {
+    let mut enabled = false;
+    let mut line_top = true;
+    for i in 0..tokens.len() {
+        if line_top && tokens[i].kind == TokenKind::Not && i + 1 < tokens.len()
+            && tokens[i + 1].kind == TokenKind::Word && tokens[i + 1].value.eq_str("インデント構文") {
+            enabled = true;
+            let pos = tokens[i].pos;
+            tokens[i] = Token::new_comment("!インデント構文", pos);
+            tokens[i + 1] = Token::new_comment("", pos);
+        }
+        line_top = tokens[i].kind == TokenKind::Eol;
+    }
+    (tokens, enabled)
+}
+
+/// トークンの前にある字下げの幅を数える (半角空白は1、全角空白は2、タブは4。空白以外があればNone)
+fn count_indent(lines: &[Vec<char>], pos: &TokenPos) -> Option<usize> {
+    let line = lines.get((pos.row - 1) as usize)?;
+    let head = line.get(..(pos.col - 1) as usize)?;
+    let mut n = 0;
+    for c in head {
+        n += match c {
+            ' ' => 1,
+            '　' => 2,
+            '\t' => 4,
+            _ => return None,
+        };
+    }
+    Some(n)
+}
+
+#[cfg(test)]
+mod test_indent {
+    use super::*;
+    use crate::tokenizer::tokenize_test;
+
+    fn convert_str(src: &str) -> String {
+        match convert(src, tokenize_test(src), "test") {
+            Ok(tokens) => tokens_string(&tokens),
+            Err(e) => e,
+        }
+    }
+    #[test]
+    fn test_convert() {
+        // 『!インデント構文』がなければそのまま
+        assert_eq!(convert_str("もしAならば\n  B"), "[もし][Word:A/ならば][Eol][Word:B]");
+        assert_eq!(convert_str("!インデント構文\nもしAならば\n  B\nC"),
+            "[Comment:!インデント構文][Comment:][Eol][もし][Word:A/ならば][Eol][Word:B][Eol][ここまで][Word:C]");
+        // 全角空白も字下げとして扱う
+        assert_eq!(convert_str("!インデント構文\nもしAならば\n　B\n違えば\n　　C"),
+            "[Comment:!インデント構文][Comment:][Eol][もし][Word:A/ならば][Eol][Word:B][Eol][違えば][Eol][Word:C][ここまで]");
+        // 字下げが揃っていない
+        let res = convert_str("!インデント構文\nもしAならば\n    B\n  C");
+        assert!(res.contains("インデントが揃っていません"), "{}", res);
+        assert!(res.contains("(test:4:3)"), "{}", res);
+        // ブロックを開かない行の後で字下げした
+        let res = convert_str("!インデント構文\nA=1\n  B=2");
+        assert!(res.contains("インデントが揃っていません"), "{}", res);
+        assert!(res.contains("(test:3:3)"), "{}", res);
+        assert_eq!(convert_str("!インデント構文\n3回\n  B\nC"),
+            "[Comment:!インデント構文][Comment:][Eol][Int:3][Kai][Eol][Word:B][Eol][ここまで][Word:C]");
+        // 括弧の中の改行は字下げとして扱わない
+        assert_eq!(convert_str("!インデント構文\nA={\n  \"a\":1\n}\nB"),
+            "[Comment:!インデント構文][Comment:][Eol][Word:A][=][{][Eol][String:a][:][Int:1][Eol][}][Eol][Word:B]");
+    }
+}
@@ -3,14 +3,14 @@
 use crate::strcur::StrCur;
 
 /// 助詞の一覧を定義
-pub const JOSI_LIST: [&str; 46] = [
+pub const JOSI_LIST: [&str; 47] = [
   // 参考 <https://github.com/kujirahand/nadesiko3/blob/master/src/nako_josi_list.js>
   // もし文で使う[助詞]
   "でなければ", "なければ", "ならば", "なら", "たら", "れば",
   // 一般的な助詞
   "について", "くらい", "なのか", "までを", "までの", 
   "による", "とは", "から", "まで", "だけ", 
-  "より", "ほど", "など", "いて", "えて", 
+  "より", "ほど", "など", "ずつ", "いて", "えて", 
   "きて", "けて", "して", "って", "にて", 
   "みて", "めて", "ねて", "では", "には", 
   "は~", "んで", "は", "を", "に", 
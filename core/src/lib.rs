@@ -29,15 +29,21 @@ pub mod strcur;
 pub mod token;
 pub mod kanautils;
 pub mod tokenizer;
+pub mod indent;
+pub mod dncl;
 pub mod josi_list;
 pub mod parser;
 pub mod reserved_words;
 pub mod tokencur;
+pub mod bigint;
+pub mod decimal;
 pub mod node;
+pub mod host;
 pub mod operator;
 pub mod bytecode_gen;
 pub mod bytecode_run;
 pub mod runner;
+pub mod session;
 pub mod sys_function;
 pub mod sys_function_debug;
 
@@ -1,6 +1,12 @@
 //! 構文解析後のノードを定義
 use std::{collections::HashMap, cell::RefCell, rc::Rc};
-use std::cell::RefMut;
+use std::cell::{Ref, RefMut};
+use std::any::{Any, TypeId};
+use std::cmp::Ordering;
+use crate::bigint::BigInt;
+use crate::decimal::Decimal;
+use crate::host::HostFunc;
+use crate::runner;
 
 /// ノードの種類
 #[allow(dead_code)]
@@ -22,6 +28,10 @@ pub enum NodeKind {
     CallUserFunc,
     If,
     Kai,
+    While,
+    Foreach,
+    Switch,
+    Try,
     Break,
     Continue,
     Return,
@@ -29,6 +39,11 @@ pub enum NodeKind {
     ArrayCreate,
     ArrayRef,
     ArrayLet,
+    DictCreate,
+    FuncRef, // 関数の値
+    CallFuncValue, // 関数の値の呼び出し F(引数)
+    CallMethod, // 型のメソッド呼び出し (値)の(メソッド名)
+    RecordField, // 型のフィールド参照 (値)の(フィールド名) ... 型の値でなければ同じ名前の関数を呼ぶ
 }
 
 /// ソースコード情報を表す構造体
@@ -62,7 +77,7 @@ impl NodePos {
 }
 
 /// ノード構造体
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Node {
     pub kind: NodeKind,
     pub value: NodeValue,
@@ -89,6 +104,10 @@ impl Node {
             NodeKind::CallUserFunc => format!("Usr:{}", self.value.to_string()),
             NodeKind::If => format!("If:{}", self.value.to_string()),
             NodeKind::Kai => format!("N回:{}", self.value.to_string()),
+            NodeKind::While => format!("間:{}", self.value.to_string()),
+            NodeKind::Foreach => format!("反復:{}", self.value.to_string()),
+            NodeKind::Switch => format!("条件分岐:{}", self.value.to_string()),
+            NodeKind::Try => format!("エラー監視:{}", self.value.to_string()),
             NodeKind::Break => String::from("Break"),
             NodeKind::Continue => String::from("Continue"),
             NodeKind::For => String::from("For"),
@@ -96,6 +115,11 @@ impl Node {
             NodeKind::ArrayCreate => format!("配列生成"),
             NodeKind::ArrayRef => format!("配列参照"),
             NodeKind::ArrayLet => format!("配列代入"),
+            NodeKind::DictCreate => String::from("辞書生成"),
+            NodeKind::FuncRef => self.value.to_string(),
+            NodeKind::CallFuncValue => format!("関数呼出:{}", self.value.to_string()),
+            NodeKind::CallMethod => format!("メソッド:{}", self.value.to_string()),
+            NodeKind::RecordField => format!("フィールド:{}", self.value.to_string()),
             // _ => format!("{:?}", self.kind),
         }
     }
@@ -149,25 +173,33 @@ impl Node {
     }
 }
 
+/// べき乗を任意精度の整数で計算する上限(ビット数)
+const MAX_BIGINT_POW_BITS: usize = 1 << 20;
+
 // I to B => (i != FALSE_VALUE)
 const FALSE_VALUE:isize = 0;
 const TRUE_VALUE:isize = 1;
 
 /// ノードの値を定義したもの
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,PartialEq)]
 pub enum NodeValue {
     Empty,
     S(String),
     I(isize),
+    BigInt(BigInt), // isizeで表せない整数 (計算が桁あふれした時に使う)
     F(f64),
+    Decimal(Decimal), // 10進数の小数 (誤差なく計算する)
     B(bool),
     A(Vec<NodeValue>),
+    H(NodeDict),
+    R(String, NodeDict), // 型の値 (型名, フィールド)
     Link(Rc<RefCell<NodeValue>>),
     NodeList(Vec<Node>),
     LetVar(NodeValueParamLet),
     GetVar(NodeVarInfo),
     Operator(NodeValueParamOperator),
     CallFunc(String, usize, Vec<Node>), // 関数(FuncNo, Args) CallFuncNo link to context.CallFuncs[FuncNo]
+    Func(NodeVarInfo), // 関数の値 (関数を登録した変数を指す)
 }
 impl NodeValue {
     pub fn from_str(v: &str) -> Self {
@@ -178,9 +210,13 @@ impl NodeValue {
             NodeValue::Empty => String::from(""),
             NodeValue::S(v) => format!("{}", v),
             NodeValue::I(v) => format!("{}", v),
+            NodeValue::BigInt(v) => v.to_string(),
             NodeValue::F(v) => format!("{}", v),
+            NodeValue::Decimal(v) => v.to_string(),
             NodeValue::B(v) => if *v { String::from("真") } else { String::from("偽") },
             NodeValue::A(v) => format!("A[len({})]", v.len()),
+            NodeValue::H(v) => v.to_string(),
+            NodeValue::R(name, v) => format!("{}{}", name, v.to_string()),
             NodeValue::Link(v) => {
                 let b: RefMut<NodeValue> = v.borrow_mut();
                 format!("Link:{}", b.to_string())
@@ -190,12 +226,18 @@ impl NodeValue {
             NodeValue::Operator(op) => format!("({})[{}]", op.flag, nodes_to_string(&op.nodes, ",")),
             NodeValue::GetVar(v) => format!("{}", v.name.clone()),
             NodeValue::CallFunc(name, _no, nodes) => format!("{}({})", name, nodes_to_string(&nodes, ",")),
+            NodeValue::Func(info) => format!("関数『{}』", info.name),
             // _ => String::from(""),
         }
     }
+    pub fn is_number(&self) -> bool {
+        matches!(self, NodeValue::I(_) | NodeValue::F(_) | NodeValue::BigInt(_) | NodeValue::Decimal(_))
+    }
     pub fn to_bool(&self) -> bool {
         match self {
             NodeValue::B(v) => *v,
+            NodeValue::BigInt(v) => !v.is_zero(),
+            NodeValue::Decimal(v) => !v.is_zero(),
             _ => {
                 let v = self.to_int(0);
                 v != FALSE_VALUE
@@ -207,7 +249,9 @@ impl NodeValue {
             NodeValue::Empty => def_value,
             NodeValue::S(v) => v.parse().unwrap_or(def_value),
             NodeValue::I(v) => *v,
+            NodeValue::BigInt(v) => if v.is_negative() { isize::MIN } else { isize::MAX },
             NodeValue::F(v) => *v as isize,
+            NodeValue::Decimal(v) => v.to_isize(),
             NodeValue::CallFunc(_, v, _) => *v as isize,
             NodeValue::B(v) => if *v { TRUE_VALUE } else { FALSE_VALUE },
             _ => def_value,
@@ -218,11 +262,20 @@ impl NodeValue {
             NodeValue::Empty => def_value,
             NodeValue::S(v) => v.parse().unwrap_or(def_value),
             NodeValue::I(v) => *v as f64,
+            NodeValue::BigInt(v) => v.to_f64(),
+            NodeValue::Decimal(v) => v.to_f64(),
             NodeValue::F(v) => *v as f64,
             NodeValue::B(v) => if *v { TRUE_VALUE as f64 } else { FALSE_VALUE as f64 }
             _ => def_value,
         }
     }
+    /// リンク(参照渡しの値)なら参照先の値を返す
+    pub fn get_link_value(&self) -> NodeValue {
+        match self {
+            NodeValue::Link(v) => v.borrow().get_link_value(),
+            _ => self.clone(),
+        }
+    }
     pub fn to_nodes(&self) -> Vec<Node> {
         match self {
             NodeValue::NodeList(nodes) => return nodes.clone(),
@@ -246,6 +299,54 @@ impl NodeValue {
             _ => None
         }
     }
+    /// 配列なら要素番号、辞書ならキーで要素を得る
+    pub fn get_index(&self, index: &NodeValue) -> Option<NodeValue> {
+        match self {
+            NodeValue::A(nlist) => nlist.get(index.to_int(0) as usize).cloned(),
+            NodeValue::H(dict) | NodeValue::R(_, dict) => dict.get(&index.to_string()).cloned(),
+            _ => None,
+        }
+    }
+    pub fn get_index_mut(&mut self, index: &NodeValue) -> Option<&mut NodeValue> {
+        match self {
+            NodeValue::A(nlist) => nlist.get_mut(index.to_int(0) as usize),
+            NodeValue::H(dict) | NodeValue::R(_, dict) => dict.get_mut(&index.to_string()),
+            _ => None,
+        }
+    }
+    /// 配列なら要素番号、辞書ならキーで要素を設定する (空の値なら配列か辞書を作る)
+    pub fn set_index(&mut self, index: &NodeValue, value: NodeValue) -> bool {
+        if let NodeValue::Empty = self {
+            *self = match index {
+                NodeValue::S(_) => NodeValue::H(NodeDict::new()),
+                _ => NodeValue::A(vec![]),
+            };
+        }
+        match self {
+            NodeValue::A(_) => self.set_array_index(index.to_int(0) as usize, value),
+            NodeValue::H(dict) => {
+                dict.set(&index.to_string(), value);
+                true
+            },
+            // 型の値は定義されたフィールドにだけ設定できる
+            NodeValue::R(_, dict) => match dict.get_mut(&index.to_string()) {
+                Some(v) => { *v = value; true },
+                None => false,
+            },
+            _ => false,
+        }
+    }
+    /// 辞書や配列の中で使う文字列表現 (文字列を引用符で囲む)
+    pub fn to_json_string(&self) -> String {
+        match self {
+            NodeValue::S(v) => format!("\"{}\"", v.replace('\\', "\\\\").replace('"', "\\\"")),
+            NodeValue::A(list) => {
+                let items: Vec<String> = list.iter().map(|v| v.to_json_string()).collect();
+                format!("[{}]", items.join(","))
+            },
+            _ => self.to_string(),
+        }
+    }
     pub fn set_array_index(&mut self, index: usize, value: NodeValue) -> bool {
         match self {
             NodeValue::A(nlist) => {
@@ -263,17 +364,77 @@ impl NodeValue {
 
 /// NodeValue 同士の計算を行うメソッドを定義
 impl NodeValue {
+    /// 任意精度の整数を値にする (isizeに収まればI)
+    pub fn from_bigint(v: BigInt) -> NodeValue {
+        match v.to_isize() {
+            Some(i) => NodeValue::I(i),
+            None => NodeValue::BigInt(v),
+        }
+    }
+    /// 整数(IかBigInt)であれば任意精度の整数を返す
+    pub fn to_bigint(&self) -> Option<BigInt> {
+        match self {
+            NodeValue::I(v) => Some(BigInt::from_isize(*v)),
+            NodeValue::BigInt(v) => Some(v.clone()),
+            _ => None,
+        }
+    }
+    /// 整数同士の計算 ... isizeで計算して桁あふれしたら任意精度の整数で計算する (整数同士でなければNone)
+    fn calc_int(left: &NodeValue, right: &NodeValue, checked: fn(isize, isize) -> Option<isize>, big: fn(&BigInt, &BigInt) -> BigInt) -> Option<NodeValue> {
+        if let (NodeValue::I(lv), NodeValue::I(rv)) = (left, right) {
+            if let Some(v) = checked(*lv, *rv) { return Some(NodeValue::I(v)); }
+        }
+        let (lv, rv) = (left.to_bigint()?, right.to_bigint()?);
+        Some(NodeValue::from_bigint(big(&lv, &rv)))
+    }
+    /// 10進数の小数であれば返す (整数は小数に変換する)
+    pub fn to_decimal(&self) -> Option<Decimal> {
+        match self {
+            NodeValue::I(v) => Some(Decimal::from_isize(*v)),
+            NodeValue::Decimal(v) => Some(*v),
+            _ => None,
+        }
+    }
+    /// 10進数の小数で計算する値の組か (片方が小数でもう片方が小数か整数)
+    pub fn is_decimal_pair(left: &NodeValue, right: &NodeValue) -> bool {
+        (matches!(left, NodeValue::Decimal(_)) || matches!(right, NodeValue::Decimal(_)))
+            && left.to_decimal().is_some() && right.to_decimal().is_some()
+    }
+    /// 10進数の小数の計算 ... 片方が小数でもう片方が小数か整数なら誤差なく計算する (桁あふれしたらNone)
+    fn calc_decimal(left: &NodeValue, right: &NodeValue, op: fn(&Decimal, &Decimal) -> Option<Decimal>) -> Option<NodeValue> {
+        if !Self::is_decimal_pair(left, right) { return None; }
+        let v = op(&left.to_decimal()?, &right.to_decimal()?)?;
+        Some(NodeValue::Decimal(v))
+    }
+    /// 整数同士・10進数の小数同士の大小比較 (比較できなければNone)
+    fn cmp_int(left: &NodeValue, right: &NodeValue) -> Option<Ordering> {
+        match (left, right) {
+            (NodeValue::I(lv), NodeValue::I(rv)) => Some(lv.cmp(rv)),
+            // 10進数の小数と整数
+            (NodeValue::Decimal(_), _) | (_, NodeValue::Decimal(_)) => Some(left.to_decimal()?.cmp(&right.to_decimal()?)),
+            _ => Some(left.to_bigint()?.cmp(&right.to_bigint()?)),
+        }
+    }
     pub fn calc_plus(left: &NodeValue, right: &NodeValue) -> NodeValue {
+        if let Some(v) = Self::calc_int(left, right, isize::checked_add, BigInt::add) { return v; }
+        if let Some(v) = Self::calc_decimal(left, right, Decimal::checked_add) { return v; }
         match (left, right) {
             // number
-            (NodeValue::I(lv), NodeValue::I(rv)) => NodeValue::I(lv + rv),
+            (NodeValue::BigInt(_) | NodeValue::Decimal(_), _) | (_, NodeValue::BigInt(_) | NodeValue::Decimal(_)) => NodeValue::F(left.to_float(0.0) + right.to_float(0.0)),
             (NodeValue::F(lv), NodeValue::I(rv)) => NodeValue::F(lv + *rv as f64),
             (NodeValue::I(lv), NodeValue::F(rv)) => NodeValue::F(*lv as f64 + rv),
             (NodeValue::F(lv), NodeValue::F(rv)) => NodeValue::F(lv + rv),
             // string
             (NodeValue::S(lv), NodeValue::S(rv)) => NodeValue::S(format!("{}{}", lv, rv)),
             // string + number
-            (NodeValue::S(lv), NodeValue::I(rv)) => NodeValue::I(lv.parse().unwrap_or(0) as isize + rv),
+            // 数値に変換できない文字列はEmpty (実行時エラーにする)
+            (NodeValue::S(lv), NodeValue::I(_)) => match BigInt::parse(lv.trim()) {
+                Some(v) => Self::calc_plus(&NodeValue::from_bigint(v), right),
+                None => match lv.trim().parse::<f64>() {
+                    Ok(v) => Self::calc_plus(&NodeValue::F(v), right),
+                    Err(_) => NodeValue::Empty,
+                },
+            },
             (NodeValue::S(lv), NodeValue::F(rv)) => NodeValue::F(lv.parse().unwrap_or(0.0) as f64 + rv),
             // other
             _ => NodeValue::Empty,
@@ -284,18 +445,23 @@ impl NodeValue {
         NodeValue::S(s)
     }
     pub fn calc_minus(left: &NodeValue, right: &NodeValue) -> NodeValue {
+        if let Some(v) = Self::calc_int(left, right, isize::checked_sub, BigInt::sub) { return v; }
+        if let Some(v) = Self::calc_decimal(left, right, Decimal::checked_sub) { return v; }
         match right {
-            NodeValue::I(rv) => NodeValue::I(left.to_int(0) - rv),
-            NodeValue::F(rv) => NodeValue::F(left.to_float(0.0) - rv),
+            NodeValue::I(_) if !matches!(left, NodeValue::F(_) | NodeValue::Decimal(_)) => Self::calc_minus(&NodeValue::I(left.to_int(0)), right),
+            NodeValue::I(_) | NodeValue::F(_) | NodeValue::BigInt(_) | NodeValue::Decimal(_) => NodeValue::F(left.to_float(0.0) - right.to_float(0.0)),
             _ => NodeValue::Empty,
         }
     }
     pub fn calc_mul(left: &NodeValue, right: &NodeValue) -> NodeValue {
+        if let Some(v) = Self::calc_int(left, right, isize::checked_mul, BigInt::mul) { return v; }
+        if let Some(v) = Self::calc_decimal(left, right, Decimal::checked_mul) { return v; }
         match (left, right) {
-            (NodeValue::I(lv), NodeValue::I(rv)) => NodeValue::I(lv * rv),
+            (NodeValue::Decimal(lv), _) => NodeValue::F(lv.to_f64() * right.to_float(0.0)),
             (NodeValue::I(lv), _) => NodeValue::F((*lv as f64) * right.to_float(0.0)),
             (NodeValue::F(lv), _) => NodeValue::F(*lv * right.to_float(0.0)),
-            (NodeValue::S(lv), NodeValue::I(times)) => NodeValue::S(Self::repeat_str(lv, *times as usize)),
+            (NodeValue::BigInt(lv), _) => NodeValue::F(lv.to_f64() * right.to_float(0.0)),
+            (NodeValue::S(lv), NodeValue::I(times)) => NodeValue::S(Self::repeat_str(lv, (*times).max(0) as usize)),
             (_, _) => NodeValue::Empty,
         }
     }
@@ -307,18 +473,34 @@ impl NodeValue {
         res
     }
     pub fn calc_div(left: &NodeValue, right: &NodeValue) -> NodeValue {
+        if let Some(v) = Self::calc_decimal(left, right, Decimal::checked_div) { return v; }
+        // 整数同士で割り切れるなら誤差なく整数で計算する
+        if let (Some(lv), Some(rv)) = (left.to_bigint(), right.to_bigint()) {
+            if let Some((q, r)) = lv.div_rem(&rv) {
+                if r.is_zero() { return NodeValue::from_bigint(q); }
+            }
+        }
         match (left, right) {
             (NodeValue::I(lv), NodeValue::I(rv)) => NodeValue::F(*lv as f64 / *rv as f64),
             (NodeValue::I(lv), NodeValue::F(rv)) => NodeValue::F((*lv as f64) / *rv as f64),
             (NodeValue::F(lv), NodeValue::I(rv)) => NodeValue::F((*lv as f64) / *rv as f64),
             (NodeValue::F(lv), NodeValue::F(rv)) => NodeValue::F((*lv as f64) / *rv as f64),
             (NodeValue::S(_), _) => NodeValue::F(left.to_float(0.0) / right.to_float(0.0)),
+            (NodeValue::BigInt(_) | NodeValue::Decimal(_), _) | (_, NodeValue::BigInt(_) | NodeValue::Decimal(_)) => NodeValue::F(left.to_float(0.0) / right.to_float(0.0)),
             (_, _) => NodeValue::Empty,
         }
     }
     pub fn calc_mod(left: &NodeValue, right: &NodeValue) -> NodeValue {
+        // 整数を0で割った余りは小数と同じくNaNにする
+        if let (Some(lv), Some(rv)) = (left.to_bigint(), right.to_bigint()) {
+            return match lv.div_rem(&rv) {
+                Some((_, r)) => NodeValue::from_bigint(r),
+                None => NodeValue::F(f64::NAN),
+            };
+        }
+        if let Some(v) = Self::calc_decimal(left, right, Decimal::checked_rem) { return v; }
         match (left, right) {
-            (NodeValue::I(lv), NodeValue::I(rv)) => NodeValue::I(*lv % *rv),
+            (NodeValue::BigInt(_) | NodeValue::Decimal(_), _) | (_, NodeValue::BigInt(_) | NodeValue::Decimal(_)) => NodeValue::F(left.to_float(0.0) % right.to_float(0.0)),
             (NodeValue::I(lv), NodeValue::F(rv)) => NodeValue::F((*lv as f64) % *rv as f64),
             (NodeValue::F(lv), NodeValue::I(rv)) => NodeValue::F((*lv as f64) % *rv as f64),
             (NodeValue::F(lv), NodeValue::F(rv)) => NodeValue::F((*lv as f64) % *rv as f64),
@@ -327,21 +509,37 @@ impl NodeValue {
         }
     }
     pub fn calc_eq(left: &NodeValue, right: &NodeValue) -> NodeValue {
-        NodeValue::B(left.to_int(0) == right.to_int(0))
+        let b = match (left, right) {
+            // 文字列同士は文字列として比較
+            (NodeValue::S(lv), NodeValue::S(rv)) => lv == rv,
+            // 数値に変換できない文字列は数値と等しくない
+            (NodeValue::S(s), v) | (v, NodeValue::S(s)) if v.is_number() && s.trim().parse::<f64>().is_err() => false,
+            (NodeValue::F(_), _) | (_, NodeValue::F(_)) => left.to_float(0.0) == right.to_float(0.0),
+            (NodeValue::BigInt(_) | NodeValue::Decimal(_), _) | (_, NodeValue::BigInt(_) | NodeValue::Decimal(_)) => match Self::cmp_int(left, right) {
+                Some(ord) => ord == Ordering::Equal,
+                None => left.to_float(0.0) == right.to_float(0.0),
+            },
+            (_, _) => left.to_int(0) == right.to_int(0),
+        };
+        NodeValue::B(b)
     }
     pub fn calc_noteq(left: &NodeValue, right: &NodeValue) -> NodeValue {
-        NodeValue::B(left.to_int(0) != right.to_int(0))
+        NodeValue::B(!Self::calc_eq(left, right).to_bool())
     }
     pub fn calc_gt(left: &NodeValue, right: &NodeValue) -> NodeValue {
+        if let Some(ord) = Self::cmp_int(left, right) { return NodeValue::B(ord == Ordering::Greater); }
         NodeValue::B(left.to_float(0.0) > right.to_float(0.0))
     }
     pub fn calc_gteq(left: &NodeValue, right: &NodeValue) -> NodeValue {
+        if let Some(ord) = Self::cmp_int(left, right) { return NodeValue::B(ord != Ordering::Less); }
         NodeValue::B(left.to_float(0.0) >= right.to_float(0.0))
     }
     pub fn calc_lt(left: &NodeValue, right: &NodeValue) -> NodeValue {
+        if let Some(ord) = Self::cmp_int(left, right) { return NodeValue::B(ord == Ordering::Less); }
         NodeValue::B(left.to_float(0.0) < right.to_float(0.0))
     }
     pub fn calc_lteq(left: &NodeValue, right: &NodeValue) -> NodeValue {
+        if let Some(ord) = Self::cmp_int(left, right) { return NodeValue::B(ord != Ordering::Greater); }
         NodeValue::B(left.to_float(0.0) <= right.to_float(0.0))
     }
     pub fn calc_and(left: &NodeValue, right: &NodeValue) -> NodeValue {
@@ -352,22 +550,91 @@ impl NodeValue {
     }
     pub fn calc_pow(left: &NodeValue, right: &NodeValue) -> NodeValue {
         match (left, right) {
-            (NodeValue::I(l),NodeValue::I(r)) => NodeValue::I((*l).pow(*r as u32)),
-            (NodeValue::F(l),NodeValue::I(r)) => NodeValue::F((*l).powi(*r as i32)),
+            (NodeValue::I(_) | NodeValue::BigInt(_), NodeValue::I(r)) if *r >= 0 => {
+                if let (NodeValue::I(l), Ok(r)) = (left, u32::try_from(*r)) {
+                    if let Some(v) = l.checked_pow(r) { return NodeValue::I(v); }
+                }
+                let l = left.to_bigint().unwrap_or(BigInt::zero());
+                // 底が0と±1なら指数がどれだけ大きくても結果は決まる
+                match l.to_isize() {
+                    Some(0) => return NodeValue::I(if *r == 0 { 1 } else { 0 }),
+                    Some(1) => return NodeValue::I(1),
+                    Some(-1) => return NodeValue::I(if *r % 2 == 0 { 1 } else { -1 }),
+                    _ => {},
+                }
+                // 結果の桁数が大きすぎる場合は小数で計算する
+                match u32::try_from(*r) {
+                    Ok(r) if l.bits().saturating_mul(r as usize) <= MAX_BIGINT_POW_BITS => NodeValue::from_bigint(l.pow(r)),
+                    _ => NodeValue::F(l.to_f64().powf(*r as f64)),
+                }
+            },
+            (NodeValue::I(_) | NodeValue::BigInt(_) | NodeValue::F(_) | NodeValue::Decimal(_), NodeValue::I(r)) => match i32::try_from(*r) {
+                Ok(r) => NodeValue::F(left.to_float(0.0).powi(r)),
+                Err(_) => NodeValue::F(left.to_float(0.0).powf(*r as f64)),
+            },
             (NodeValue::F(l),NodeValue::F(r)) => NodeValue::F((*l).powf(*r)),
+            (NodeValue::I(_) | NodeValue::BigInt(_) | NodeValue::Decimal(_), NodeValue::F(r)) => NodeValue::F(left.to_float(0.0).powf(*r)),
+            (NodeValue::I(_) | NodeValue::BigInt(_) | NodeValue::F(_) | NodeValue::Decimal(_), NodeValue::Decimal(_) | NodeValue::BigInt(_)) => NodeValue::F(left.to_float(0.0).powf(right.to_float(0.0))),
             (_, _) => NodeValue::Empty,
         }
     }
 }
 
-#[derive(Debug,Clone)]
+/// 挿入順を保持する辞書型
+#[derive(Debug,Clone,PartialEq)]
+pub struct NodeDict {
+    keys: Vec<String>,
+    values: HashMap<String, NodeValue>,
+}
+impl NodeDict {
+    pub fn new() -> Self {
+        Self { keys: vec![], values: HashMap::new() }
+    }
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+    pub fn get(&self, key: &str) -> Option<&NodeValue> {
+        self.values.get(key)
+    }
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut NodeValue> {
+        self.values.get_mut(key)
+    }
+    pub fn set(&mut self, key: &str, value: NodeValue) {
+        if self.values.insert(String::from(key), value).is_none() {
+            self.keys.push(String::from(key));
+        }
+    }
+    pub fn remove(&mut self, key: &str) -> Option<NodeValue> {
+        let v = self.values.remove(key)?;
+        self.keys.retain(|k| k != key);
+        Some(v)
+    }
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+    /// 挿入順に(キー, 値)を列挙する
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &NodeValue)> {
+        self.keys.iter().map(move |k| (k, &self.values[k]))
+    }
+    pub fn to_string(&self) -> String {
+        let items: Vec<String> = self.iter()
+            .map(|(k, v)| format!("{}:{}", NodeValue::S(k.clone()).to_json_string(), v.to_json_string()))
+            .collect();
+        format!("{{{}}}", items.join(","))
+    }
+}
+
+#[derive(Debug,Clone,PartialEq)]
 pub struct NodeValueParamLet {
     pub var_info: NodeVarInfo,
     pub value_node: Vec<Node>,
     pub index_node: Vec<Node>,
 }
 
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,PartialEq)]
 pub struct NodeVarInfo {
     pub level: usize,
     pub no: usize,
@@ -378,12 +645,18 @@ pub struct NodeVarInfo {
 pub struct NodeVarMeta {
     pub read_only: bool,
     pub kind: NodeVarKind,
+    /// ユーザー関数のローカル変数名(変数番号順) - 呼び出し時にこの順で領域を確保する
+    pub frame: Vec<String>,
+    /// ユーザー関数のローカル変数のうち定数の変数番号
+    pub frame_read_only: Vec<usize>,
 }
 impl NodeVarMeta {
     pub fn new() -> Self {
         Self {
             read_only: false,
             kind: NodeVarKind::Empty,
+            frame: vec![],
+            frame_read_only: vec![],
         }
     }
 }
@@ -470,6 +743,10 @@ impl NodeScopeList {
         }
         None
     }
+    pub fn get_var_meta_mut(&mut self, info: &NodeVarInfo) -> Option<&mut NodeVarMeta> {
+        let scope: &mut NodeScope = &mut self.scopes[info.level];
+        scope.var_metas.get_mut(info.no)
+    }
 }
 
 
@@ -494,6 +771,22 @@ impl NodeScope {
         obj.set_var("それ", NodeValue::Empty);
         obj
     }
+    /// 変数名の一覧から関数呼び出し用のスコープを作る (構文解析時と変数番号を一致させる)
+    pub fn from_names(names: &[String]) -> Self {
+        let mut obj = Self::new();
+        for name in names.iter() {
+            obj.set_var(name, NodeValue::Empty);
+        }
+        obj
+    }
+    /// 変数名を変数番号順に得る
+    pub fn get_names(&self) -> Vec<String> {
+        let mut names = vec![String::new(); self.var_values.len()];
+        for (name, no) in self.var_names.iter() {
+            names[*no] = name.clone();
+        }
+        names
+    }
 
     pub fn get_var(&self, name: &str) -> NodeValue {
         let no = match self.find_var(name) {
@@ -524,7 +817,7 @@ impl NodeScope {
     }
 }
 
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,PartialEq)]
 pub struct NodeValueParamOperator {
     pub flag: char,
     pub nodes: Vec<Node>,
@@ -539,6 +832,7 @@ pub enum NodeVarKind {
     String,
     SysFunc(Vec<SysArg>),
     UserFunc(Vec<SysArg>),
+    Type(Vec<SysArg>), // 型の定義 (フィールドの一覧)
     Array,
     Dict,
 }
@@ -591,9 +885,17 @@ pub struct NodeContext {
     pub try_continue: Option<usize>,
     pub try_return: Option<usize>,
     pub return_level: usize,
+    /// 実行中の繰り返しが使っている変数(回数や対象など)の名前
+    pub loop_vars: Vec<&'static str>,
     pub print_log: String,
     /// set print function
     pub print_fn: Option<fn(&str)>,
+    /// set input function (引数は入力を促す文字列)
+    pub input_fn: Option<fn(&str) -> String>,
+    /// 実行中のシステム関数の呼び出し位置
+    pub sysfunc_pos: NodePos,
+    /// ホストアプリが設定するデータ (型ごとに一つ。コンテキストを複製しても共有する)
+    user_data: HashMap<TypeId, Rc<RefCell<dyn Any>>>,
 }
 
 impl NodeContext {
@@ -611,8 +913,12 @@ impl NodeContext {
             try_continue: None,
             try_return: None,
             return_level: 0,
+            loop_vars: vec![],
             print_log: String::new(),
             print_fn: None,
+            input_fn: None,
+            sysfunc_pos: NodePos::empty(),
+            user_data: HashMap::new(),
         }
     }
     // for file management
@@ -663,33 +969,116 @@ impl NodeContext {
     pub fn throw_runtime_error(&mut self, msg: String, pos: NodePos) {
         self.throw_error(NodeErrorKind::RuntimeError, NodeErrorLevel::Error, msg, pos);
     }
+    /// システム関数の中から実行時エラーを発生させる
+    pub fn throw_sysfunc_error(&mut self, msg: String) {
+        let pos = self.sysfunc_pos;
+        self.throw_runtime_error(msg, pos);
+    }
+    /// 記録されているエラー(警告やヒントも含む)の件数
+    pub fn get_error_len(&self) -> usize {
+        self.errors.len()
+    }
+    /// index番目以降のエラーを取り除いて返す (エラー監視で使う)
+    pub fn pop_errors(&mut self, index: usize) -> Vec<NodeError> {
+        if index >= self.errors.len() { return vec![]; }
+        let removed: Vec<NodeError> = self.errors.drain(index..).collect();
+        for e in removed.iter() {
+            if let NodeErrorLevel::Error = e.level { self.error_count -= 1; }
+        }
+        removed
+    }
+    /// エラーを全て消去する
+    pub fn clear_errors(&mut self) {
+        self.errors.clear();
+        self.error_count = 0;
+    }
     // for scope variables
     pub fn find_var_info(&self, name: &str) -> Option<NodeVarInfo> {
         self.scopes.find_var(name)
     }
+    /// 実行時に変数が属するスコープの番号を得る (ローカル変数は実行中の関数のスコープ)
+    fn get_scope_index(&self, info: &NodeVarInfo) -> usize {
+        if info.level >= 2 { self.scopes.len() - 1 } else { info.level }
+    }
     pub fn get_var_value(&self, info: &NodeVarInfo) -> Option<NodeValue> {
-        if info.level >= 2 {
-            // local
-            let local: &NodeScope = self.scopes.scopes.last().unwrap();
-            if local.var_values.len() > info.no {
-                return Some(local.var_values[info.no].clone())
-            } else {
-                return None;
-            }
-        } else {
-            // system or global 
-            self.scopes.get_var_value(info)
+        let scope = &self.scopes.scopes[self.get_scope_index(info)];
+        scope.var_values.get(info.no).map(|v| v.get_link_value())
+    }
+    pub fn get_var_value_mut(&mut self, info: &NodeVarInfo) -> Option<&mut NodeValue> {
+        let index = self.get_scope_index(info);
+        self.scopes.scopes[index].var_values.get_mut(info.no)
+    }
+    /// 構文解析で決めた(スコープ, 変数番号)に値を設定する
+    pub fn set_var_value(&mut self, info: &NodeVarInfo, value: NodeValue) {
+        let index = self.get_scope_index(info);
+        let scope = &mut self.scopes.scopes[index];
+        match scope.var_values.get_mut(info.no) {
+            Some(v) => *v = value,
+            // 構文解析時と異なるコンテキストで実行した場合は名前で設定する
+            None => { scope.set_var(&info.name, value); },
         }
     }
     #[allow(dead_code)]
     pub fn get_var_meta(&self, info: &NodeVarInfo) -> Option<NodeVarMeta> {
         self.scopes.get_var_meta(info)
     }
-    // add system func
-    pub fn add_sysfunc(&mut self, name: &str, args: Vec<SysArg>, func: SysFuncType) -> usize {
+    /// 実行時に変数が定数かどうか
+    pub fn is_read_only(&self, info: &NodeVarInfo) -> bool {
+        let scope = &self.scopes.scopes[self.get_scope_index(info)];
+        scope.var_metas.get(info.no).is_some_and(|meta| meta.read_only)
+    }
+    // for host application
+    /// グローバル変数の値を得る
+    pub fn get_global(&self, name: &str) -> Result<NodeValue, NodeError> {
+        let scope = &self.scopes.scopes[1];
+        match scope.find_var(name) {
+            Some(no) => Ok(scope.var_values[*no].get_link_value()),
+            None => Err(self.host_error(format!("グローバル変数『{}』が見当たりません。", name))),
+        }
+    }
+    /// グローバル変数に値を設定する (変数がなければ作る。実行前に設定すればプログラムから使える)
+    pub fn set_global(&mut self, name: &str, value: NodeValue) -> Result<(), NodeError> {
+        let scope = &self.scopes.scopes[1];
+        if let Some(no) = scope.find_var(name) {
+            let meta = &scope.var_metas[*no];
+            if meta.read_only || matches!(meta.kind, NodeVarKind::UserFunc(_) | NodeVarKind::Type(_)) {
+                return Err(self.host_error(format!("『{}』は定数か関数なので値を設定できません。", name)));
+            }
+        }
+        self.scopes.scopes[1].set_var(name, value);
+        Ok(())
+    }
+    /// 関数を名前で呼び出す (実行時エラーがあればそのエラーを返す)
+    pub fn call_func(&mut self, name: &str, args: Vec<NodeValue>) -> Result<NodeValue, NodeError> {
+        // ローカル変数には隠されないようにグローバル、システムの順に探す
+        let info = [1, 0].iter().find_map(|level| {
+            self.scopes.scopes[*level].find_var(name)
+                .map(|no| NodeVarInfo { level: *level, no: *no, name: String::from(name) })
+        });
+        let info = match info {
+            Some(info) => info,
+            None => return Err(self.host_error(format!("関数『{}』が見当たりません。", name))),
+        };
+        let error_len = self.errors.len();
+        let error_count = self.error_count;
+        let result = runner::call_func_value(self, &NodeValue::Func(info), args, NodePos::empty());
+        if self.error_count > error_count {
+            let errors = self.pop_errors(error_len);
+            if let Some(err) = errors.into_iter().find(|e| matches!(e.level, NodeErrorLevel::Error)) {
+                return Err(err);
+            }
+        }
+        Ok(result)
+    }
+    fn host_error(&self, msg: String) -> NodeError {
+        NodeError::new(NodeErrorKind::RuntimeError, NodeErrorLevel::Error, msg, 0, 0, self.get_filename(0))
+    }
+    /// add system func (関数のほか、値を捕捉したクロージャも登録できる)
+    pub fn add_sysfunc<F>(&mut self, name: &str, args: Vec<SysArg>, func: F) -> usize
+    where F: Fn(&mut NodeContext, Vec<NodeValue>) -> Option<NodeValue> + 'static {
         // add func to sysfuncs
         let sys_no = self.sysfuncs.len();
-        let sfi = SysFuncInfo{ func };
+        let sfi = SysFuncInfo{ func: Rc::new(func), name: String::from(name), args_len: args.len() };
         self.sysfuncs.push(sfi);
         // add name to scope
         let scope = &mut self.scopes.scopes[0];
@@ -698,6 +1087,33 @@ impl NodeContext {
         scope.var_metas[no].kind = NodeVarKind::SysFunc(args);
         sys_no     
     }
+    /// Rustの関数を登録する (引数は型を確かめて変換し、変換できなければ実行時エラーにする)
+    /// 例: `ctx.add_func("加算", &[&["と"], &["を"]], |a: i64, b: i64| a + b);`
+    /// 助詞の数と関数の引数の数が違う場合は登録せずにエラーを記録する
+    pub fn add_func<Args: 'static, F>(&mut self, name: &str, josi: &[&[&str]], func: F) -> usize
+    where F: HostFunc<Args> + 'static {
+        match self.try_add_func(name, josi, func) {
+            Ok(no) => no,
+            Err(msg) => {
+                self.throw_error(NodeErrorKind::RuntimeError, NodeErrorLevel::Error, msg, NodePos::empty());
+                0
+            }
+        }
+    }
+    /// Rustの関数を登録する (助詞の数と関数の引数の数が違えば登録せずにエラーを返す)
+    pub fn try_add_func<Args: 'static, F>(&mut self, name: &str, josi: &[&[&str]], func: F) -> Result<usize, String>
+    where F: HostFunc<Args> + 'static {
+        if josi.len() != func.args_len() {
+            return Err(format!("『{}』の助詞と引数の数が違います。", name));
+        }
+        let func_name = String::from(name);
+        Ok(self.add_sysfunc(name, sysargs(josi), move |ctx, args| {
+            match func.invoke(&func_name, &args) {
+                Ok(v) => Some(v),
+                Err(msg) => { ctx.throw_sysfunc_error(msg); None },
+            }
+        }))
+    }
     pub fn add_sysvar(&mut self, name: &str, value: NodeValue) -> usize {
         let no = self.add_sysconst(name, value);
         let scope = &mut self.scopes.scopes[0];
@@ -710,6 +1126,7 @@ impl NodeContext {
             NodeValue::S(_) => { NodeVarKind::String },
             NodeValue::I(_) => { NodeVarKind::Number },
             NodeValue::F(_) => { NodeVarKind::Number },
+            NodeValue::H(_) => { NodeVarKind::Dict },
             _ => { NodeVarKind::Empty }
         };
         let scope = &mut self.scopes.scopes[0];
@@ -718,6 +1135,24 @@ impl NodeContext {
         scope.var_metas[no].kind = kind;
         no
     }
+    /// ホストアプリのデータを設定する (同じ型のデータがあれば置き換える)
+    pub fn set_user_data<T: Any>(&mut self, data: T) {
+        self.user_data.insert(TypeId::of::<T>(), Rc::new(RefCell::new(data)));
+    }
+    /// ホストアプリのデータを借用する
+    pub fn user_data<T: Any>(&self) -> Option<Ref<'_, T>> {
+        let data = self.user_data.get(&TypeId::of::<T>())?.try_borrow().ok()?;
+        Ref::filter_map(data, |v| v.downcast_ref::<T>()).ok()
+    }
+    /// ホストアプリのデータを書き換えるために借用する
+    pub fn user_data_mut<T: Any>(&self) -> Option<RefMut<'_, T>> {
+        let data = self.user_data.get(&TypeId::of::<T>())?.try_borrow_mut().ok()?;
+        RefMut::filter_map(data, |v| v.downcast_mut::<T>()).ok()
+    }
+    /// ホストアプリのデータを取り除く (データがあればtrue)
+    pub fn remove_user_data<T: Any>(&mut self) -> bool {
+        self.user_data.remove(&TypeId::of::<T>()).is_some()
+    }
     /// print
     pub fn print(&mut self, str: &str) {
         // add to log
@@ -735,20 +1170,40 @@ impl NodeContext {
         let disp = format!("{}\n", str);
         self.print(&disp);
     }
+    /// input ... 一行読んで末尾の改行を除いて返す
+    pub fn input(&mut self, prompt: &str) -> String {
+        let line = match &self.input_fn {
+            Some(f) => f(prompt),
+            None => {
+                // default
+                print!("{}", prompt);
+                let _ = std::io::Write::flush(&mut std::io::stdout());
+                let mut line = String::new();
+                let _ = std::io::stdin().read_line(&mut line);
+                line
+            }
+        };
+        String::from(line.trim_end_matches(['\r', '\n']))
+    }
 
 }
 
-type SysFuncType = fn(&mut NodeContext, Vec<NodeValue>) -> Option<NodeValue>;
+pub type SysFuncType = Rc<dyn Fn(&mut NodeContext, Vec<NodeValue>) -> Option<NodeValue>>;
 
 #[derive(Clone)]
 pub struct SysFuncInfo {
     pub func: SysFuncType,
+    pub name: String,
+    /// 登録した引数の数
+    pub args_len: usize,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct SysArg {
     pub name: String, 
-    pub josi_list: Vec<String>
+    pub josi_list: Vec<String>,
+    /// 引数を省略した時の既定値
+    pub default: Option<Node>,
 }
 
 pub fn sysargs(args: &[&[&str]]) -> Vec<SysArg> {
@@ -760,7 +1215,7 @@ pub fn sysargs(args: &[&[&str]]) -> Vec<SysArg> {
             arg_res.push(String::from(*a));
         }
         let a_name = String::from(a);
-        result.push(SysArg{ name: a_name, josi_list: arg_res });
+        result.push(SysArg{ name: a_name, josi_list: arg_res, default: None });
         a = ((a as u8) + 1u8) as char;
     }
     result
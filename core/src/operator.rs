@@ -51,8 +51,11 @@ pub fn get_node_priority(node_v: &Node) -> i8 {
         NodeKind::Number => OP_PRIORITY_VALUE,
         NodeKind::String => OP_PRIORITY_VALUE,
         NodeKind::GetVarGlobal => OP_PRIORITY_VALUE,
+        NodeKind::GetVarLocal => OP_PRIORITY_VALUE,
         NodeKind::CallSysFunc => OP_PRIORTY_FUNCTION,
         NodeKind::CallUserFunc => OP_PRIORTY_FUNCTION,
+        NodeKind::CallMethod => OP_PRIORTY_FUNCTION,
+        NodeKind::RecordField => OP_PRIORITY_VALUE,
         _ => OP_PRIORITY_VALUE,
     }
 }
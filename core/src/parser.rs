@@ -5,11 +5,24 @@ use crate::node::*;
 use crate::tokencur::TokenCur;
 use crate::operator;
 use crate::josi_list;
+use crate::kanautils;
+use crate::tokenizer;
+use crate::bigint::BigInt;
+use crate::decimal::Decimal;
+use crate::nvalue::NValue;
+use crate::reserved_words;
+
+/// ひらがなの関数名を活用した形で呼ぶときの語幹の最小の文字数
+const CONJUGATION_MIN_STEM: usize = 1;
+/// 語幹に続く活用語尾 (みる→みて、たす→たして、かく→かいて、よむ→よんで、かう→かって)
+const CONJUGATION_ENDINGS: [&str; 13] = ["て", "た", "して", "した", "し", "いて", "いた", "いで", "いだ", "んで", "んだ", "って", "った"];
 
 pub struct Parser {
     pub context: NodeContext,
     cur: TokenCur,
     stack: Vec<Node>,
+    /// 無名関数の中からは見えない外側の関数のローカル変数
+    outer_scopes: Vec<NodeScope>,
 }
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
@@ -17,6 +30,7 @@ impl Parser {
         Self {
             cur: TokenCur::new(tokens),
             stack: vec![],
+            outer_scopes: vec![],
             context,
         }
     }
@@ -24,6 +38,7 @@ impl Parser {
         Self {
             cur: TokenCur::new(tokens),
             stack: vec![],
+            outer_scopes: vec![],
             context,
         }
     }
@@ -71,6 +86,8 @@ impl Parser {
             }
             old_index = self.cur.index;
         }
+        // 最後の文でエラーがあった場合
+        if self.has_error() { return Err(self.get_error_str()); }
         Ok(result)
     }
 
@@ -94,6 +111,7 @@ impl Parser {
             if self.has_error() { return Err(self.get_error_str()); }
             if self.cur.eq_kind(TokenKind::BlockEnd) { break; }
             if self.cur.eq_kind(TokenKind::Else) { break; }
+            if self.cur.eq_kind(TokenKind::Catch) { break; }
             // 連続で文を読む
             if let Some(node) = self.sentence() {
                 nodes.push(node);
@@ -126,6 +144,18 @@ impl Parser {
         return Node::new(kind, NodeValue::Empty, None, self.pos(t))
     }
 
+    /// 変数参照のノードを作る (関数内のローカル変数ならGetVarLocal)
+    fn new_get_var_node(&self, info: NodeVarInfo, josi: Option<String>, pos: NodePos) -> Node {
+        let kind = if info.level >= 2 { NodeKind::GetVarLocal } else { NodeKind::GetVarGlobal };
+        Node::new(kind, NodeValue::GetVar(info), josi, pos)
+    }
+
+    /// 変数代入のノードを作る (関数内のローカル変数ならLetVarLocal)
+    fn new_let_var_node(&self, param: NodeValueParamLet, pos: NodePos) -> Node {
+        let kind = if param.var_info.level >= 2 { NodeKind::LetVarLocal } else { NodeKind::LetVarGlobal };
+        Node::new(kind, NodeValue::LetVar(param), None, pos)
+    }
+
     fn sentence(&mut self) -> Option<Node> {
         // 「ここまで」があれば抜ける
         if self.cur.eq_kind(TokenKind::BlockEnd) { return None; }
@@ -141,6 +171,8 @@ impl Parser {
         if let Some(node) = self.check_let() { return Some(node); }
         // もし文
         if let Some(node) = self.check_if() { return Some(node); }
+        // エラー監視
+        if self.cur.eq_kind(TokenKind::Try) { return self.check_try(); }
         // 関数定義
         if self.cur.eq_kind(TokenKind::DefFunc) { return self.check_def_func(false); }
         // 抜ける・続ける・戻る
@@ -153,6 +185,7 @@ impl Parser {
             return Some(self.new_simple_node(NodeKind::Continue, &t));
         }
         // 記号
+        if let Some(node) = self.check_token_error() { return Some(node); }
         if let Some(node) = self.check_flag() { return Some(node); }
         // トークンの連続＋命令の場合(Aして、Bして、C…を検出する)
         while self.cur.can_read() {
@@ -161,8 +194,25 @@ impl Parser {
             // スタックに載せるべき値が見当たらなければループから抜ける
             if !self.check_value() { break; }
             if self.cur.eq_kind(TokenKind::Dainyu) { return self.check_dainyu(); }
+            // (条件)の間 ... 条件に関数呼び出しを含むことがあるので先に確認
+            if self.cur.eq_kind(TokenKind::While) {
+                return self.check_while();
+            }
+            if self.cur.eq_kind(TokenKind::Foreach) {
+                return self.check_foreach();
+            }
+            if self.cur.eq_kind(TokenKind::Switch) {
+                return self.check_switch();
+            }
+            // (変数)を(値)増やす・減らす
+            if self.cur.eq_kind(TokenKind::Inc) || self.cur.eq_kind(TokenKind::Dec) {
+                return self.check_inc_dec();
+            }
             // call function?
-            if self.stack_last_eq(NodeKind::CallSysFunc) || self.stack_last_eq(NodeKind::CallUserFunc) {
+            // 関数の値の呼び出し F(引数) は助詞がなければ文として扱う
+            let is_call_value = self.stack_last_eq(NodeKind::CallFuncValue) &&
+                (self.stack_last_josi_eq("") || self.stack.last().is_some_and(|n| n.is_renbun_josi()));
+            if self.stack_last_eq(NodeKind::CallSysFunc) || self.stack_last_eq(NodeKind::CallUserFunc) || self.stack_last_eq(NodeKind::CallMethod) || is_call_value {
                 let callfunc = self.stack.pop().unwrap_or(Node::new_nop());
                 // 連文の「して」がある場合、もう一文読む
                 if callfunc.is_renbun_josi() {
@@ -216,6 +266,13 @@ impl Parser {
         None
     }
     
+    fn check_token_error(&mut self) -> Option<Node> {
+        if !self.cur.eq_kind(TokenKind::Error) { return None; }
+        let t = self.cur.next();
+        self.throw_error(t.value.to_string(), self.pos(&t));
+        None
+    }
+
     fn check_flag(&mut self) -> Option<Node> {
         if !self.cur.eq_kind(TokenKind::Flag) { return None; }
         let t = self.cur.next(); // flag
@@ -226,7 +283,12 @@ impl Parser {
     fn check_for(&mut self) -> Option<Node> {
         let kai_t = self.cur.next(); // skip 繰り返す
         
-        // [Iを][0から][9まで]繰り返す
+        // [Iを][0から][9まで][1ずつ]繰り返す
+        let step_node = if self.stack_last_josi_eq("ずつ") {
+            self.stack.pop().unwrap_or(Node::new_nop())
+        } else {
+            Node::new_nop()
+        };
         let made_node = self.stack.pop().unwrap_or(Node::new_nop());
         let kara_node = self.stack.pop().unwrap_or(Node::new_nop());
         let loop_node = if self.stack_last_josi_eq("を") || self.stack_last_josi_eq("で") {
@@ -234,6 +296,10 @@ impl Parser {
         } else {
             Node::new_nop()
         };
+        // 定数は繰り返す変数にできない
+        if let NodeValue::GetVar(info) = &loop_node.value {
+            if self.check_read_only(info, &kai_t) { return None; }
+        }
 
         // 繰り返す内容
         self.skip_comma_comment();
@@ -269,6 +335,7 @@ impl Parser {
                 loop_node,
                 kara_node,
                 made_node,
+                step_node,
                 Node::new(NodeKind::NodeList, NodeValue::NodeList(body_nodes), None, self.pos(&kai_t)),
             ]), None, self.pos(&kai_t));
         Some(for_node)
@@ -280,6 +347,7 @@ impl Parser {
             self.cur.next(); // skip "繰り返す"
         }
         let kaisu_node = self.stack.pop().unwrap_or(Node::new_nop());
+        if self.check_loop_vars_read_only(&["回数"], &kai_t) { return None; }
         self.skip_comma_comment();
         let mut single_sentence = true;
         if self.cur.eq_kind(TokenKind::BlockBegin) {
@@ -315,6 +383,76 @@ impl Parser {
         Some(kai_node)
     }
 
+    /// 繰り返しなどの本文を読む (単文または「ここまで」までの複文)
+    fn read_block_body(&mut self) -> Option<Vec<Node>> {
+        self.skip_comma_comment();
+        let mut single_sentence = true;
+        if self.cur.eq_kind(TokenKind::BlockBegin) {
+            single_sentence = false;
+            self.cur.next(); // ここから
+        }
+        if self.cur.eq_kind(TokenKind::Eol) {
+            single_sentence = false;
+            self.cur.next(); // LF
+        }
+        if single_sentence {
+            let node = match self.sentence() {
+                Some(node) => node,
+                None => Node::new_nop(),
+            };
+            return Some(vec![node]);
+        }
+        let body_nodes = match self.get_sentence_list() {
+            Ok(nodes) => nodes,
+            Err(_) => return None,
+        };
+        if self.cur.eq_kind(TokenKind::BlockEnd) {
+            self.cur.next(); // skip ここまで
+        }
+        Some(body_nodes)
+    }
+
+    fn check_while(&mut self) -> Option<Node> {
+        let while_t = self.cur.next(); // skip "間"
+        // (条件)の間
+        let cond_node = match self.stack.pop() {
+            Some(node) => node,
+            None => {
+                self.throw_error_token("『(条件)の間』の書式で条件を指定してください", while_t);
+                return None;
+            }
+        };
+        let body_nodes = self.read_block_body()?;
+        let while_node = Node::new(
+            NodeKind::While,
+            NodeValue::NodeList(vec![
+                cond_node,
+                Node::new_node_list(body_nodes, self.pos(&while_t)),
+            ]), None, self.pos(&while_t));
+        Some(while_node)
+    }
+
+    fn check_foreach(&mut self) -> Option<Node> {
+        let foreach_t = self.cur.next(); // skip "反復"
+        // (配列)を反復
+        let target_node = match self.stack.pop() {
+            Some(node) => node,
+            None => {
+                self.throw_error_token("『(配列)を反復』の書式で反復する対象を指定してください", foreach_t);
+                return None;
+            }
+        };
+        if self.check_loop_vars_read_only(&["対象", "対象キー"], &foreach_t) { return None; }
+        let body_nodes = self.read_block_body()?;
+        let foreach_node = Node::new(
+            NodeKind::Foreach,
+            NodeValue::NodeList(vec![
+                target_node,
+                Node::new_node_list(body_nodes, self.pos(&foreach_t)),
+            ]), None, self.pos(&foreach_t));
+        Some(foreach_node)
+    }
+
     fn check_comment(&mut self) -> Option<Node> {
         if !self.cur.eq_kind(TokenKind::Comment) { return None; }
         let t = self.cur.next();
@@ -488,6 +626,81 @@ impl Parser {
         Some(if_node)
     }
 
+    fn check_switch(&mut self) -> Option<Node> {
+        let switch_t = self.cur.next(); // skip "条件分岐"
+        let switch_pos = self.pos(&switch_t);
+        // (値)で条件分岐
+        let target_node = match self.stack.pop() {
+            Some(node) => node,
+            None => {
+                self.throw_error_token("『(値)で条件分岐』の書式で比較する値を指定してください", switch_t);
+                return None;
+            }
+        };
+        // nodes = [値, 違えばの本文, (条件1, 本文1), (条件2, 本文2), ...]
+        let mut nodes = vec![target_node, Node::new_node_list(vec![], switch_pos)];
+        loop {
+            self.skip_comma_comment();
+            self.skip_eol_comment();
+            if !self.cur.can_read() {
+                self.throw_error_token("『条件分岐』の終わりに『ここまで』がありません", switch_t);
+                return None;
+            }
+            // 条件分岐の終わり
+            if self.cur.eq_kind(TokenKind::BlockEnd) {
+                self.cur.next(); // skip ここまで
+                break;
+            }
+            // 違えば
+            if self.cur.eq_kind(TokenKind::Else) {
+                let else_t = self.cur.next(); // skip 違えば
+                let body_nodes = self.read_block_body()?;
+                nodes[1] = Node::new_node_list(body_nodes, self.pos(&else_t));
+                continue;
+            }
+            // (条件)ならば
+            let cond_t = self.cur.peek();
+            if !self.check_value() {
+                self.throw_error_token("『条件分岐』では『(値)ならば』の書式で条件を指定してください", cond_t);
+                return None;
+            }
+            let cond_node = self.stack.pop().unwrap_or(Node::new_nop());
+            if josi_list::is_josi_mosi(&cond_node.get_josi_str()) != Some(true) {
+                self.throw_error_token("『条件分岐』では『(値)ならば』の書式で条件を指定してください", cond_t);
+                return None;
+            }
+            let body_nodes = self.read_block_body()?;
+            nodes.push(cond_node);
+            nodes.push(Node::new_node_list(body_nodes, self.pos(&cond_t)));
+        }
+        Some(Node::new(NodeKind::Switch, NodeValue::NodeList(nodes), None, switch_pos))
+    }
+
+    fn check_try(&mut self) -> Option<Node> {
+        let try_t = self.cur.next(); // skip "エラー監視"
+        // 監視する本文
+        self.skip_comma_comment();
+        let try_nodes = match self.get_sentence_list() {
+            Ok(nodes) => nodes,
+            Err(_) => return None,
+        };
+        self.skip_eol_comment();
+        if !self.cur.eq_kind(TokenKind::Catch) {
+            self.throw_error_token("『エラー監視』に対応する『エラーならば』がありません", try_t);
+            return None;
+        }
+        let catch_t = self.cur.next(); // skip "エラーならば"
+        // エラーが起きた時の本文
+        let catch_nodes = self.read_block_body()?;
+        let try_node = Node::new(
+            NodeKind::Try,
+            NodeValue::NodeList(vec![
+                Node::new_node_list(try_nodes, self.pos(&try_t)),
+                Node::new_node_list(catch_nodes, self.pos(&catch_t)),
+            ]), None, self.pos(&try_t));
+        Some(try_node)
+    }
+
     fn check_value(&mut self) -> bool {
         // 値を一つ取得
         if !self.check_value_one() {
@@ -510,6 +723,7 @@ impl Parser {
             if self.check_value_one() {
                 if self.stack_last_eq(NodeKind::CallSysFunc) { return true; }
                 if self.stack_last_eq(NodeKind::CallUserFunc) { return true; }
+                if self.stack_last_eq(NodeKind::CallMethod) { return true; }
                 let josi_opt = self.stack_last_josi();
                 let josi_s = match josi_opt {
                     None => return true,
@@ -562,13 +776,13 @@ impl Parser {
             }
             break;
         }
-        // "="
-        if !self.cur.eq_kind(TokenKind::Eq) { // "="
+        // "=" or "+=" など
+        if !is_let_flag(self.cur.peek_kind()) {
             // 配列アクセスだけど代入文ではなかった！！
             self.cur.index = old_index; // 巻き戻す
             return None;
         }
-        self.cur.next(); // "="
+        let eq_t = self.cur.next(); // "="
         // value
         let value_node_b = self.check_value();
         if !value_node_b {
@@ -576,7 +790,7 @@ impl Parser {
             self.throw_error_token(&msg, word_t);
             return None;
         }
-        let value_node = self.stack.pop().unwrap_or(Node::new_nop());
+        let mut value_node = self.stack.pop().unwrap_or(Node::new_nop());
         let var_info = match self.context.find_var_info(&word_t.value.to_string()) {
             Some(info) => info,
             None => {
@@ -586,6 +800,15 @@ impl Parser {
                 return None;
             }
         };
+        if self.check_read_only(&var_info, &word_t) { return None; }
+        // 複合代入 A[i]+=値 なら A[i]+値 を代入する
+        if eq_t.kind != TokenKind::Eq {
+            let pos = self.pos(&word_t);
+            let mut ref_list = vec![self.new_get_var_node(var_info.clone(), None, pos)];
+            ref_list.extend(index_vec.iter().cloned());
+            let ref_node = Node::new(NodeKind::ArrayRef, NodeValue::NodeList(ref_list), None, pos);
+            value_node = Node::new_operator(eq_t.as_char(), ref_node, value_node, None, pos);
+        }
         let node_params = NodeValueParamLet{
             var_info,
             value_node: vec![value_node],
@@ -595,7 +818,48 @@ impl Parser {
         Some(let_array_node)
     }
 
+    /// 定数(読み取り専用の変数)への代入であればエラーを報告してtrueを返す
+    fn check_read_only(&mut self, info: &NodeVarInfo, t: &Token) -> bool {
+        let read_only = match self.context.get_var_meta(info) {
+            Some(meta) => meta.read_only,
+            None => false,
+        };
+        if read_only {
+            self.throw_error(format!("定数『{}』には値を代入できません。", info.name), self.pos(t));
+        }
+        read_only
+    }
+
+    /// 繰り返しで値が設定される変数(回数や対象など)が同じスコープの定数ならエラー
+    fn check_loop_vars_read_only(&mut self, names: &[&str], t: &Token) -> bool {
+        let local = self.context.scopes.len() - 1;
+        for name in names {
+            if let Some(info) = self.context.find_var_info(name) {
+                if info.level == local && self.check_read_only(&info, t) { return true; }
+            }
+        }
+        false
+    }
+
     fn check_let(&mut self) -> Option<Node> {
+        // 定数の宣言 '定数 (定数名)=(値)' がある?
+        let mut def_const = false;
+        if self.cur.eq_kind(TokenKind::DefConst) {
+            let teisu = self.cur.next();
+            if !self.cur.eq_kinds(&[TokenKind::Word, TokenKind::Eq]) {
+                self.throw_error(String::from("『定数 (定数名)=(値)』の書式で定数を宣言してください。"), self.pos(&teisu));
+                return None;
+            }
+            let word: Token = self.cur.peek();
+            let name = word.value.to_string();
+            // 同じスコープに定数があれば再定義できない
+            let local = self.context.scopes.scopes.len() - 1;
+            if let Some(info) = self.context.find_var_info(&name) {
+                if info.level == local && self.check_read_only(&info, &word) { return None; }
+            }
+            self.context.scopes.set_value_local_scope(&name, NodeValue::Empty);
+            def_const = true;
+        }
         // ローカル変数の宣言 '変数' がある?
         if self.cur.eq_kind(TokenKind::DefVar) {
             let dainyu = self.cur.peek();
@@ -617,10 +881,12 @@ impl Parser {
             Some(node) => return Some(node),
             None => {}
         }
-        // 代入文か?
-        if !self.cur.eq_kinds(&[TokenKind::Word, TokenKind::Eq]) { return None; }
+        // 代入文か? (word "=" または word "+=" など)
+        if self.cur.peek_kind() != TokenKind::Word { return None; }
+        let is_let = self.cur.tokens.get(self.cur.index + 1).is_some_and(|t| is_let_flag(t.kind));
+        if !is_let { return None; }
         let word: Token = self.cur.next();
-        self.cur.next(); // eq
+        let eq_t: Token = self.cur.next(); // eq
 
         // 値を取得する
         if !self.check_value() { // error
@@ -642,25 +908,81 @@ impl Parser {
         let mut var_info:NodeVarInfo = match self.context.find_var_info(&var_name) {
             Some(info) => info,
             None => {
-                // グローバル変数を生成
-                self.context.scopes.set_value(1, &var_name, NodeValue::Empty);
-                let info = self.context.find_var_info(&var_name);
-                info.unwrap()
+                // 変数を生成 (関数の中ならローカル変数、それ以外ならグローバル変数)
+                self.context.scopes.set_value_local_scope(&var_name, NodeValue::Empty)
             },
         };
-        // 値を得る
         var_info.name = var_name.clone();
+        // 複合代入 N+=値 なら N+値 を代入する
+        let value = if eq_t.kind != TokenKind::Eq {
+            let pos = self.pos(&word);
+            let var_node = self.new_get_var_node(var_info.clone(), None, pos);
+            Node::new_operator(eq_t.as_char(), var_node, value, None, pos)
+        } else { value };
+        if def_const {
+            // 定数として登録
+            if let Some(meta) = self.context.scopes.get_var_meta_mut(&var_info) {
+                meta.read_only = true;
+            }
+        } else if self.check_read_only(&var_info, &word) {
+            return None;
+        }
+        // 値を得る
         // println!("let:{:?}", var_info);
         let node_value_let = NodeValueParamLet {
             var_info,
             value_node: vec![value],
             index_node: vec![],
         };
-        let let_node = Node::new(
-            NodeKind::LetVarGlobal, 
-            NodeValue::LetVar(node_value_let),
-            None, self.pos(&word));
-        Some(let_node)
+        Some(self.new_let_var_node(node_value_let, self.pos(&word)))
+    }
+
+    fn check_inc_dec(&mut self) -> Option<Node> {
+        // VAR{a}をVALUE{b}増やす・減らす (値を省略すると1)
+        let inc_t = self.cur.next();
+        let flag = if inc_t.kind == TokenKind::Inc { '+' } else { '-' };
+        let pos = self.pos(&inc_t);
+        let mut value_node = Node::new(NodeKind::Int, NodeValue::I(1), None, pos);
+        if !self.stack_last_josi_eq("を") {
+            value_node = self.stack.pop().unwrap_or(value_node);
+            value_node.josi = None;
+        }
+        let mut var_node = match self.stack.pop() {
+            Some(node) => node,
+            None => {
+                self.throw_error(format!("『{}』の対象となる変数がありません。", inc_t.value.to_string()), pos);
+                return None;
+            }
+        };
+        var_node.josi = None;
+        match (var_node.kind, var_node.value.clone()) {
+            (NodeKind::GetVarGlobal | NodeKind::GetVarLocal, NodeValue::GetVar(var_info)) => {
+                if self.check_read_only(&var_info, &inc_t) { return None; }
+                let value = Node::new_operator(flag, var_node, value_node, None, pos);
+                let node_value_let = NodeValueParamLet{var_info, value_node: vec![value], index_node: vec![]};
+                Some(self.new_let_var_node(node_value_let, pos))
+            },
+            (NodeKind::ArrayRef | NodeKind::RecordField, NodeValue::NodeList(list)) => {
+                let var_info = match list.first().map(|n| &n.value) {
+                    Some(NodeValue::GetVar(info)) => info.clone(),
+                    _ => {
+                        self.throw_error(format!("『{}』の対象が変数ではありません。", inc_t.value.to_string()), pos);
+                        return None;
+                    }
+                };
+                if self.check_read_only(&var_info, &inc_t) { return None; }
+                // フィールドの参照は(値, フィールド名, 同じ名前の関数)
+                let index_end = if var_node.kind == NodeKind::RecordField { 2 } else { list.len() };
+                let index_node = list[1..index_end].to_vec();
+                let value = Node::new_operator(flag, var_node, value_node, None, pos);
+                let node_value_let = NodeValueParamLet{var_info, value_node: vec![value], index_node};
+                Some(Node::new(NodeKind::ArrayLet, NodeValue::LetVar(node_value_let), None, pos))
+            },
+            _ => {
+                self.throw_error(format!("『{}』の対象が変数ではありません。", inc_t.value.to_string()), pos);
+                None
+            }
+        }
     }
 
     fn check_dainyu(&mut self) -> Option<Node> {
@@ -679,7 +1001,7 @@ impl Parser {
             value_node = b;
         }
         // get variable name
-        let var_name = if var_node.kind == NodeKind::GetVarGlobal {
+        let var_name = if var_node.kind == NodeKind::GetVarGlobal || var_node.kind == NodeKind::GetVarLocal {
             match var_node.value {
                 NodeValue::GetVar(v) => { v.name },
                 _ => { "それ".to_string() }
@@ -688,18 +1010,12 @@ impl Parser {
         // println!("{}に{:?}を代入", var_name, value_node);
         let mut var_info:NodeVarInfo = match self.context.find_var_info(&var_name) {
             Some(v) => v,
-            None => {
-                self.context.scopes.set_value(1, &var_name, NodeValue::Empty);
-                NodeVarInfo{level:1, no:0, name: String::from("それ")}
-            }
+            None => self.context.scopes.set_value_local_scope(&var_name, NodeValue::Empty),
         };
         var_info.name = var_name;
+        if self.check_read_only(&var_info, &dainyu) { return None; }
         let node_value_let = NodeValueParamLet{var_info, value_node: vec![value_node], index_node: vec![]};
-        let let_node = Node::new(
-            NodeKind::LetVarGlobal, 
-            NodeValue::LetVar(node_value_let), 
-            None, self.pos(&dainyu));
-        Some(let_node)
+        Some(self.new_let_var_node(node_value_let, self.pos(&dainyu)))
     }
 
     fn check_paren(&mut self) -> bool {
@@ -733,8 +1049,13 @@ impl Parser {
         }
         if self.cur.eq_kind(TokenKind::Int) {
             let t = self.cur.next();
-            let i:isize = t.value.to_string().parse().unwrap_or(0);
-            let node = Node::new(NodeKind::Int, NodeValue::I(i), t.josi.clone(), self.pos(&t));
+            let s = t.value.to_string();
+            // isizeに収まらなければ任意精度の整数にする
+            let v = match s.parse::<isize>() {
+                Ok(i) => NodeValue::I(i),
+                Err(_) => BigInt::parse(&s).map(NodeValue::from_bigint).unwrap_or(NodeValue::I(0)),
+            };
+            let node = Node::new(NodeKind::Int, v, t.josi.clone(), self.pos(&t));
             self.stack.push(node);
             self.check_operator();
             return true;
@@ -747,6 +1068,20 @@ impl Parser {
             self.check_operator();
             return true;
         }
+        if self.cur.eq_kind(TokenKind::Decimal) {
+            let t = self.cur.next();
+            let v = match Decimal::parse(&t.value.to_string()) {
+                Some(v) => v,
+                None => {
+                    self.throw_error_token("10進数の小数の桁数が多すぎます", t);
+                    return false;
+                }
+            };
+            let node = Node::new(NodeKind::Number, NodeValue::Decimal(v), t.josi.clone(), self.pos(&t));
+            self.stack.push(node);
+            self.check_operator();
+            return true;
+        }
         if self.cur.eq_kind(TokenKind::String) {
             let t = self.cur.next();
             let node = Node::new(NodeKind::String, NodeValue::S(t.value.to_string()), t.josi.clone(), self.pos(&t));
@@ -765,6 +1100,10 @@ impl Parser {
         if self.cur.eq_kind(TokenKind::Word) {
             return self.check_variable();
         }
+        // 関数の値
+        if self.cur.eq_kind(TokenKind::Func) {
+            return self.check_func_value();
+        }
         // JSON?
         if self.cur.eq_kind(TokenKind::BracketL) {
             let t = self.cur.next();
@@ -793,24 +1132,85 @@ impl Parser {
             self.stack.push(ca);
             return true;
         }
+        // 辞書 {キー: 値, ...}
+        if self.cur.eq_kind(TokenKind::CurBracketL) {
+            return self.check_dict();
+        }
         false
     }
 
+    fn check_dict(&mut self) -> bool {
+        let t = self.cur.next(); // skip '{'
+        // nlist = [キー1, 値1, キー2, 値2, ...]
+        let mut nlist = vec![];
+        let josi;
+        loop {
+            self.skip_eol_comment();
+            if self.cur.eq_kind(TokenKind::CurBracketR) {
+                josi = self.cur.next().josi;
+                break;
+            }
+            // キー (文字列・語句・数値)
+            let key_t = self.cur.next();
+            match key_t.kind {
+                TokenKind::String | TokenKind::Word | TokenKind::Int | TokenKind::Number | TokenKind::Decimal => {},
+                _ => {
+                    self.throw_error_token("辞書データの初期化でキーが読めません", key_t);
+                    return false;
+                }
+            }
+            if !self.cur.eq_kind(TokenKind::Colon) {
+                self.throw_error_token("辞書データの初期化で『キー:値』の『:』がありません", key_t);
+                return false;
+            }
+            self.cur.next(); // skip ':'
+            self.skip_eol_comment();
+            if !self.check_value_one() {
+                self.throw_error_token("辞書データの初期化で値が読めません", key_t);
+                return false;
+            }
+            let value_node = self.stack.pop().unwrap_or(Node::new_nop());
+            nlist.push(Node::new(NodeKind::String, NodeValue::S(key_t.value.to_string()), None, self.pos(&key_t)));
+            nlist.push(value_node);
+            self.skip_eol_comment();
+            if self.cur.eq_kind(TokenKind::Comma) {
+                self.cur.next();
+            }
+        }
+        let node = Node::new(NodeKind::DictCreate, NodeValue::NodeList(nlist), josi, self.pos(&t));
+        self.stack.push(node);
+        true
+    }
+
     fn read_func_args(&mut self, func_name: &str, args: Vec<SysArg>, pos: NodePos) -> Vec<Node> {
-        let mut arg_nodes = vec![];
+        let mut arg_nodes: Vec<Option<Node>> = vec![None; args.len()];
         let mut err_msg = String::new();
         let mut sore_hokan = false;
-        // todo: 助詞を確認する
-        // 引数の数だけstackからpopする
-        for _arg in args.iter() {
-            let n = match self.stack.pop() {
+        // 引数の候補はスタックの上から引数の数だけ
+        let cand_start = self.stack.len().saturating_sub(args.len());
+        let mut cands = self.stack.split_off(cand_start);
+        // 助詞が一致する値を引数に割り当てる (後ろの引数から、後ろの値を優先)
+        for (i, arg) in args.iter().enumerate().rev() {
+            let found = cands.iter().rposition(|n| arg.josi_list.iter().any(|j| n.eq_josi(j)));
+            if let Some(index) = found {
+                arg_nodes[i] = Some(cands.remove(index));
+            }
+        }
+        // 助詞が一致しなかった引数には残りの値を順に割り当てる
+        for (i, arg) in args.iter().enumerate().rev() {
+            if arg_nodes[i].is_some() { continue; }
+            let n = match cands.pop() {
                 Some(n) => n,
                 None => {
+                    // 既定値があれば使う
+                    if let Some(default) = &arg.default {
+                        arg_nodes[i] = Some(default.clone());
+                        continue;
+                    }
                     if !sore_hokan {
                         let sore_var = self.context.find_var_info("それ").unwrap_or(NodeVarInfo{level:1, no:0, name:String::from("それ")});
-                        let sore_node = Node::new(NodeKind::GetVarGlobal,
-                            NodeValue::GetVar(sore_var), None, pos);
-                        arg_nodes.push(sore_node);
+                        let sore_node = self.new_get_var_node(sore_var, None, pos);
+                        arg_nodes[i] = Some(sore_node);
                         sore_hokan = true;
                         continue;
                     }
@@ -818,26 +1218,155 @@ impl Parser {
                     Node::new_nop()
                 }
             };
-            arg_nodes.push(n);
+            // 助詞が指定されているのに一致しなければエラー (「して」などの連文は前の結果を渡すのでよい)
+            let josi_s = n.get_josi_str();
+            if !josi_s.is_empty() && !n.is_renbun_josi() {
+                let msg = format!("関数『{}』の引数『{}』で助詞『{}』は使えません。『{}』のいずれかを指定してください。",
+                    func_name, arg.name, josi_s, arg.josi_list.join("』『"));
+                self.throw_error(msg, n.pos);
+            }
+            arg_nodes[i] = Some(n);
         }
-        arg_nodes.reverse();
         if err_msg.ne("") {
             self.context.throw_error(
                 NodeErrorKind::ParserError, NodeErrorLevel::Error,
                 err_msg, pos);    
         }
-        arg_nodes
+        arg_nodes.into_iter().map(|n| n.unwrap_or(Node::new_nop())).collect()
+    }
+
+    /// ひらがなだけの関数名は送りがなを削れないので、活用した形(かぞえる→かぞえて)から関数を探す
+    /// 「みて表示」のように活用語尾の後に語句が続けば、その語句を次のトークンとして分ける
+    fn find_user_func_conjugated(&mut self, word_t: &mut Token) -> Option<NodeVarInfo> {
+        // 代入先の語句は関数にしない
+        if self.cur.eq_kind(TokenKind::Eq) || self.cur.eq_kind(TokenKind::Dainyu) { return None; }
+        let josi = word_t.josi.clone().unwrap_or_default();
+        let surface = format!("{}{}", word_t.value.to_string(), josi);
+        let scope = &self.context.scopes.scopes[1];
+        let mut found: Option<(&String, usize, String, String)> = None;
+        for (name, no) in scope.var_names.iter() {
+            if !matches!(scope.var_metas[*no].kind, NodeVarKind::UserFunc(_)) { continue; }
+            let chars: Vec<char> = name.chars().collect();
+            // 語幹(最後の1文字を除く)が1文字以上ある、ひらがなだけの関数名
+            if chars.len() < CONJUGATION_MIN_STEM + 1 || !chars.iter().all(|c| kanautils::is_hiragana(*c)) { continue; }
+            let stem: String = chars[..chars.len() - 1].iter().collect();
+            let rest = match surface.strip_prefix(&stem) {
+                Some(rest) => rest,
+                None => continue,
+            };
+            // 語幹の後ろは活用語尾で、その後はひらがな以外の語句が続くか何もない
+            let last = chars[chars.len() - 1].to_string();
+            let ending = std::iter::once(last.as_str()).chain(CONJUGATION_ENDINGS.iter().copied())
+                .filter(|e| rest.strip_prefix(*e).is_some_and(|r| r == josi || !r.starts_with(kanautils::is_hiragana)))
+                .max_by_key(|e| e.len());
+            let ending = match ending {
+                Some(ending) => ending,
+                None => continue,
+            };
+            // 語幹が長いものを優先
+            if found.as_ref().is_none_or(|f| f.0.len() < name.len()) {
+                found = Some((name, *no, String::from(ending), String::from(&rest[ending.len()..])));
+            }
+        }
+        let (name, no, ending, remain) = found?;
+        let info = NodeVarInfo { level: 1, no, name: name.clone() };
+        // 活用語尾の後に続く語句は次のトークンにする (助詞は後ろの語句に付く)
+        let (remain, josi_consumed) = match remain.strip_suffix(josi.as_str()) {
+            Some(r) => (r.to_string(), false),
+            None => (remain.clone(), true),
+        };
+        if josi_consumed { word_t.josi = None; }
+        if !remain.is_empty() {
+            let remain_len = remain.chars().count() as i32;
+            let word_len = word_t.value.to_string().chars().count() as i32;
+            let mut pos = word_t.pos;
+            pos.start += word_len - remain_len;
+            pos.col += word_len - remain_len;
+            let kind = reserved_words::check_kind(&remain);
+            self.cur.insert_token(self.cur.index, Token::new(kind, NValue::from_string(remain), word_t.josi.take(), pos));
+        }
+        // 「て」「で」で終わる場合は連文(して)として扱う
+        if ending.ends_with('て') || ending.ends_with('で') { word_t.josi = Some(String::from("して")); }
+        Some(info)
+    }
+
+    /// 語句が型のフィールド名かメソッド名であれば、スタックの値(助詞「の」)を対象に参照・呼び出しのノードを作る
+    fn check_record_member(&mut self, word_t: &Token) -> Option<Node> {
+        let name = word_t.value.to_string();
+        let pos = self.pos(word_t);
+        let scope = &self.context.scopes.scopes[1];
+        // フィールドの参照 ... 配列参照 (値)["フィールド名"] と同じ
+        let is_field = scope.var_metas.iter().any(|meta| match &meta.kind {
+            NodeVarKind::Type(fields) => fields.iter().any(|f| f.name == name),
+            _ => false,
+        });
+        if is_field && self.stack_last_josi_eq("の") {
+            // 同じ名前の関数があれば実行時に型の値かどうかで呼び分ける
+            let func = self.context.find_var_info(&name).filter(|info| self.context.get_var_meta(info)
+                .is_some_and(|meta| matches!(meta.kind, NodeVarKind::SysFunc(_) | NodeVarKind::UserFunc(_))));
+            let mut target = self.stack.pop()?;
+            target.josi = None;
+            let key = Node::new(NodeKind::String, NodeValue::S(name), None, pos);
+            return Some(match func {
+                Some(info) => {
+                    let func_node = Node::new(NodeKind::FuncRef, NodeValue::Func(info), None, pos);
+                    Node::new(NodeKind::RecordField, NodeValue::NodeList(vec![target, key, func_node]), word_t.josi.clone(), pos)
+                },
+                None => Node::new(NodeKind::ArrayRef, NodeValue::NodeList(vec![target, key]), word_t.josi.clone(), pos),
+            });
+        }
+        // メソッドの呼び出し ... 『型名の(メソッド名)』という関数を探す (実行時に型名で呼び分ける)
+        let method_name = tokenizer::delete_okurigana_str(&name);
+        let suffix = format!("の{}", method_name);
+        let mut methods: Vec<(usize, &Vec<SysArg>)> = scope.var_names.iter().filter_map(|(func_name, no)| {
+            let type_name = func_name.strip_suffix(&suffix)?;
+            let type_no = scope.var_names.get(type_name)?;
+            if !matches!(scope.var_metas[*type_no].kind, NodeVarKind::Type(_)) { return None; }
+            match &scope.var_metas[*no].kind {
+                NodeVarKind::UserFunc(args) => Some((*no, args)),
+                _ => None,
+            }
+        }).collect();
+        if methods.is_empty() { return None; }
+        methods.sort_by_key(|(no, _)| *no);
+        // 型の値でなければ同じ名前の関数を呼ぶので、引数の形がそろっていないといけない
+        if let Some(no) = scope.var_names.get(&method_name) {
+            if let NodeVarKind::UserFunc(args) = &scope.var_metas[*no].kind { methods.push((*no, args)); }
+        }
+        let args = methods[0].1.clone();
+        let is_same = methods.iter().all(|(_, a)| a.len() == args.len() && a.iter().zip(args.iter()).all(|(a, b)| a.josi_list == b.josi_list));
+        if !is_same {
+            self.throw_error(format!("メソッド『{}』の引数の形が型や関数ごとに異なります。", method_name), pos);
+            return None;
+        }
+        let nodes = self.read_func_args(&method_name, args, pos);
+        Some(Node::new(NodeKind::CallMethod, NodeValue::CallFunc(method_name, 0, nodes), word_t.josi.clone(), pos))
     }
 
     fn check_variable(&mut self) -> bool {
         // 変数を得る
-        let word_t = self.cur.next(); // 変数名 || 関数名
+        let mut word_t = self.cur.next(); // 変数名 || 関数名
+        // 型の値のフィールド『(値)の(フィールド名)』またはメソッド『(値)の(引数)(メソッド名)』
+        if self.stack.iter().any(|n| n.eq_josi("の")) {
+            if let Some(node) = self.check_record_member(&word_t) {
+                self.stack.push(node);
+                self.check_operator();
+                return true;
+            }
+        }
         let name = &word_t.value.to_string();
         let mut info = match self.context.find_var_info(name) {
             Some(info) => info,
-            None => {
+            None => match self.find_user_func_conjugated(&mut word_t) {
+                // ひらがなの関数名を活用した形 (かぞえる→かぞえて)
+                Some(info) => info,
+                // 無名関数の中から外側の関数のローカル変数は参照できない
+                None if self.outer_scopes.iter().any(|scope| scope.find_var(name).is_some()) => {
+                    self.throw_error(format!("外側のローカル変数『{}』は使えません。", name), self.pos(&word_t));
+                    return false;
+                },
                 // 変数がなければ作る
-                self.context.scopes.set_value_local_scope(name, NodeValue::Empty)
+                None => self.context.scopes.set_value_local_scope(name, NodeValue::Empty),
             }
         };
         // 変数か関数か？
@@ -873,11 +1402,17 @@ impl Parser {
                     // 変数の参照
                     _ => {
                         info.name = String::from(name);
-                        let var_node = Node::new(
-                            NodeKind::GetVarGlobal,
-                            NodeValue::GetVar(info),
-                            word_t.josi.clone(), self.pos(&word_t));
-                        var_node
+                        let var_node = self.new_get_var_node(info, word_t.josi.clone(), self.pos(&word_t));
+                        // 変数名の直後にカッコがあれば関数の値の呼び出し F(引数)
+                        let paren_t = self.cur.peek();
+                        if word_t.josi.is_none() && paren_t.kind == TokenKind::ParenL && paren_t.pos.start == word_t.pos.end {
+                            match self.check_call_func_value(var_node, &word_t) {
+                                Some(node) => node,
+                                None => return false,
+                            }
+                        } else {
+                            var_node
+                        }
                     }
                 }
             },
@@ -888,6 +1423,7 @@ impl Parser {
         if self.cur.peek_kind() == TokenKind::BracketL {
             let mut index_vec = vec![node];
             let t = self.cur.next();
+            let mut josi;
             loop {
                 let b = self.check_value();
                 if !b {
@@ -901,14 +1437,14 @@ impl Parser {
                 let index_node = self.stack.pop().unwrap_or(Node::new_nop());
                 index_vec.push(index_node);
                 // n次元配列か？
-                self.cur.next(); // skip ']'
+                josi = self.cur.next().josi; // skip ']' (最後の『]』の助詞を使う)
                 if self.cur.peek_kind() == TokenKind::BracketL {
                     self.cur.next();
                     continue;
                 }
                 break;
             }
-            let ref_node = Node::new(NodeKind::ArrayRef, NodeValue::NodeList(index_vec), None, self.pos(&t));
+            let ref_node = Node::new(NodeKind::ArrayRef, NodeValue::NodeList(index_vec), josi, self.pos(&t));
             self.stack.push(ref_node);
         } else {
             self.stack.push(node);
@@ -986,23 +1522,57 @@ impl Parser {
                 self.cur.next(); // skip ')'
                 break;
             }
+            // 関数(A,B)のようにカンマで区切った場合
+            if self.cur.eq_kind(TokenKind::Comma) {
+                self.cur.next();
+                continue;
+            }
             if !self.cur.eq_kind(TokenKind::Word) {
                 self.throw_error_token(&format!("関数の引数定義は語句が必要です。"), self.cur.peek());
                 break;
             }
             let w = self.cur.next(); // 語句を1つ得る
+            let mut josi = w.josi.clone();
+            // 既定値 (AとBを{3}) または (AとB=3を)
+            let mut default = None;
+            if self.cur.eq_kind(TokenKind::CurBracketL) {
+                let t = self.cur.next(); // skip '{'
+                if !self.check_value() {
+                    self.throw_error_token(&format!("引数『{}』の既定値が読めません", w.value.to_string()), t);
+                    break;
+                }
+                default = self.stack.pop();
+                if !self.cur.eq_kind(TokenKind::CurBracketR) {
+                    self.throw_error_token(&format!("引数『{}』の既定値に閉じ波カッコがありません", w.value.to_string()), t);
+                    break;
+                }
+                let close_t = self.cur.next(); // skip '}'
+                if josi.is_none() { josi = close_t.josi; }
+            } else if self.cur.eq_kind(TokenKind::Eq) {
+                let t = self.cur.next(); // skip '='
+                if !self.check_value_one() {
+                    self.throw_error_token(&format!("引数『{}』の既定値が読めません", w.value.to_string()), t);
+                    break;
+                }
+                let mut node = self.stack.pop().unwrap_or(Node::new_nop());
+                // (B=3を)のように値の後ろに助詞がある場合
+                if josi.is_none() { josi = node.josi.take(); }
+                default = Some(node);
+            }
             // argsに同じ語句があるか
             let mut flag_reg = false;
             for arg in args.iter_mut() {
                 if arg.name == w.value.to_string() {
-                    arg.josi_list.push(w.josi.clone().unwrap_or(String::new()));
+                    arg.josi_list.push(josi.clone().unwrap_or(String::new()));
+                    if default.is_some() { arg.default = default.clone(); }
                     flag_reg = true;
                 }
             }
             if flag_reg == false {
                 args.push(SysArg{
                     name: w.value.to_string(), 
-                    josi_list: vec![w.josi.clone().unwrap_or(String::new())]
+                    josi_list: vec![josi.clone().unwrap_or(String::new())],
+                    default,
                 });
             }
         }
@@ -1023,17 +1593,37 @@ impl Parser {
             return None;
         }
         let name_t = self.cur.next(); // skip name
-        let name_s = name_t.value.to_string().clone();
+        // 字句解析と同じ送りがなの規則で関数名を揃える
+        let mut name_s = tokenizer::delete_okurigana_str(&name_t.value.to_string());
+        // メソッドの定義 : ●(型名)の(メソッド名)とは
+        let is_method = name_t.josi.as_deref() == Some("の") && self.cur.eq_kind(TokenKind::Word);
+        if is_method {
+            let method_t = self.cur.next();
+            name_s = format!("{}の{}", name_t.value.to_string(), tokenizer::delete_okurigana_str(&method_t.value.to_string()));
+        }
+        // 『●関数名 とは』のように「とは」を離して書いた場合
+        if self.cur.eq_kinds(&[TokenKind::Word, TokenKind::Eq]) && self.cur.peek().value.to_string() == "と" {
+            self.cur.next(); // skip と
+            self.cur.next(); // skip は
+        }
+        // 型の定義 : ●(型名)とは型 (フィールド, ...)
+        if !is_method && self.cur.eq_kind(TokenKind::Word) && self.cur.peek().value.to_string() == "型" {
+            return self.check_def_type(&name_s, &def_t);
+        }
         // 旧引数定義方法 : ●関数名(引数)
         if self.cur.eq_kind(TokenKind::ParenL) {
             args = self.read_def_func_arg();
         }
+        // メソッドは最初の引数『自身』に型の値を受け取る
+        if is_method {
+            args.insert(0, SysArg { name: String::from("自身"), josi_list: vec![String::from("の")], default: None });
+        }
         // 関数を登録 (関数はグローバル領域に確保)
         let scope = &mut self.context.scopes.scopes[1];
         // 変数に名前を登録 - 関数名をスコープに登録
-        let no = scope.set_var(&name_t.value.to_string(), NodeValue::Empty);
+        let no = scope.set_var(&name_s, NodeValue::Empty);
         // 関数番号をスコープに再登録(再帰呼び出しに対応)
-        scope.set_var(&name_t.value.to_string(), NodeValue::CallFunc(name_s.clone(), no, vec![]));
+        scope.set_var(&name_s, NodeValue::CallFunc(name_s.clone(), no, vec![]));
         let meta = &mut scope.var_metas[no];
         meta.kind = NodeVarKind::UserFunc(args.clone());
         meta.read_only = true;
@@ -1041,6 +1631,40 @@ impl Parser {
             // 本文を見ずに抜ける
             return None;
         }
+        self.read_func_body(&name_s, no, &args, &def_t)?;
+        Some(
+            Node::new(
+                NodeKind::Comment, NodeValue::S(format!("関数『{}』の定義", name_s)), None, 
+                self.pos(&def_t)
+            )
+        )
+    }
+
+    /// 型の定義『●(型名)とは型 (フィールド, ...)』を読む (型名を関数のように呼ぶと値を作る)
+    fn check_def_type(&mut self, name_s: &str, def_t: &Token) -> Option<Node> {
+        let type_t = self.cur.next(); // skip 型
+        if !self.cur.eq_kind(TokenKind::ParenL) {
+            self.throw_error_token(&format!("型『{}』の定義でフィールドを『型 (フィールド, ...)』の書式で指定してください", name_s), type_t);
+            return None;
+        }
+        let fields = self.read_def_func_arg();
+        // 型はグローバル領域に確保
+        let scope = &mut self.context.scopes.scopes[1];
+        let no = scope.set_var(name_s, NodeValue::Empty);
+        scope.set_var(name_s, NodeValue::Func(NodeVarInfo { level: 1, no, name: String::from(name_s) }));
+        let meta = &mut scope.var_metas[no];
+        meta.kind = NodeVarKind::Type(fields);
+        meta.read_only = true;
+        Some(
+            Node::new(
+                NodeKind::Comment, NodeValue::S(format!("型『{}』の定義", name_s)), None,
+                self.pos(def_t)
+            )
+        )
+    }
+
+    /// 関数本文を読んでグローバル領域の関数(変数番号no)に登録する
+    fn read_func_body(&mut self, name_s: &str, no: usize, args: &[SysArg], def_t: &Token) -> Option<()> {
         // ローカル変数をスコープに追加
         let mut local_scope = NodeScope::new();
         for arg in args.iter() {
@@ -1051,36 +1675,120 @@ impl Parser {
         let mut body_nodes = match self.get_sentence_list() {
             Ok(nodes) => nodes,
             Err(err) => {
-                self.throw_error_token(&format!("関数『{}』の定義でエラー。{}", name_t.value.to_string(), err), def_t);
+                self.throw_error_token(&format!("関数『{}』の定義でエラー。{}", name_s, err), def_t.clone());
                 return None;
             },               
         };
         // 「それで戻る」を最後に足す ← TODO: うまく「それ」が追加されていない
         let sore_var = self.context.find_var_info("それ").unwrap_or(NodeVarInfo{level:2, no:0, name:String::from("それ")});
-        let sore_node = Node::new(
-            NodeKind::GetVarGlobal,
-            NodeValue::GetVar(sore_var), None, 
-            self.pos(&name_t));
+        let sore_node = self.new_get_var_node(sore_var, None, self.pos(def_t));
         let ret_node = Node::new(
             NodeKind::Return, NodeValue::NodeList(vec![sore_node]), None, 
-            self.pos(&name_t));
+            self.pos(def_t));
         body_nodes.push(ret_node);
         // BlockEnd判定
         if self.cur.eq_kind(TokenKind::BlockEnd) {
             self.cur.next(); // skip ここまで
         }
-        // ローカルスコープから抜ける
-        self.context.scopes.pop_local();
+        // ローカルスコープから抜ける (呼び出し時に同じ変数番号で領域を確保するため変数名を記録)
+        let local_scope = self.context.scopes.pop_local().unwrap_or(NodeScope::new());
+        let meta = &mut self.context.scopes.scopes[1].var_metas[no];
+        meta.frame = local_scope.get_names();
+        meta.frame_read_only = local_scope.var_metas.iter().enumerate()
+            .filter(|(_, m)| m.read_only)
+            .map(|(i, _)| i)
+            .collect();
         // 関数本体を変数に登録
-        let func_value: NodeValue = NodeValue::CallFunc(name_s.clone(), no, body_nodes);
-        self.context.scopes.set_value(1, &name_s, func_value);
-        Some(
-            Node::new(
-                NodeKind::Comment, NodeValue::S(format!("関数『{}』の定義", name_s)), None, 
-                self.pos(&def_t)
-            )
-        )
+        let func_value: NodeValue = NodeValue::CallFunc(String::from(name_s), no, body_nodes);
+        self.context.scopes.set_value(1, name_s, func_value);
+        Some(())
+    }
+
+    /// 関数の値『関数 (関数名)』または無名関数『関数(引数)...ここまで』を読む
+    fn check_func_value(&mut self) -> bool {
+        let func_t = self.cur.next(); // skip 関数
+        // 定義済みの関数を値として参照
+        if self.cur.eq_kind(TokenKind::Word) {
+            let name_t = self.cur.next();
+            let name = name_t.value.to_string();
+            let is_func = match self.context.find_var_info(&name) {
+                Some(info) => match self.context.get_var_meta(&info) {
+                    Some(meta) => match meta.kind {
+                        NodeVarKind::SysFunc(_) | NodeVarKind::UserFunc(_) => Some(info),
+                        _ => None,
+                    },
+                    None => None,
+                },
+                None => None,
+            };
+            let info = match is_func {
+                Some(info) => info,
+                None => {
+                    self.throw_error_token(&format!("『{}』は関数ではありません", name), name_t);
+                    return false;
+                }
+            };
+            let node = Node::new(NodeKind::FuncRef, NodeValue::Func(info), name_t.josi.clone(), self.pos(&name_t));
+            self.stack.push(node);
+            return true;
+        }
+        // 無名関数の引数定義
+        let mut args: Vec<SysArg> = vec![];
+        if self.cur.eq_kind(TokenKind::ParenL) {
+            args = self.read_def_func_arg();
+        }
+        // 無名関数はグローバル領域に名前を付けて登録する (「:」を含むので利用者の語句とは重ならない)
+        let scope = &mut self.context.scopes.scopes[1];
+        let name_s = format!("無名関数:{}", scope.var_values.len());
+        let no = scope.set_var(&name_s, NodeValue::CallFunc(name_s.clone(), 0, vec![]));
+        scope.var_metas[no].kind = NodeVarKind::UserFunc(args.clone());
+        scope.var_metas[no].read_only = true;
+        // 外側の関数のローカル変数は見えないようにして本文を読む
+        let outer_len = self.outer_scopes.len();
+        let outer_scopes = self.context.scopes.scopes.split_off(2);
+        self.outer_scopes.extend(outer_scopes);
+        let result = self.read_func_body(&name_s, no, &args, &func_t);
+        let outer_scopes = self.outer_scopes.split_off(outer_len);
+        self.context.scopes.scopes.extend(outer_scopes);
+        if result.is_none() { return false; }
+        let info = NodeVarInfo { level: 1, no, name: name_s };
+        let node = Node::new(NodeKind::FuncRef, NodeValue::Func(info), None, self.pos(&func_t));
+        self.stack.push(node);
+        self.check_operator();
+        true
     }
+
+    /// 関数の値を呼び出す F(引数, ...) の引数を読む
+    fn check_call_func_value(&mut self, func_node: Node, word_t: &Token) -> Option<Node> {
+        let paren_t = self.cur.next(); // skip '('
+        let mut nodes = vec![func_node];
+        let josi;
+        loop {
+            if !self.cur.can_read() {
+                self.throw_error_token(&format!("『{}』の呼び出しで閉じカッコがありません", word_t.value.to_string()), paren_t);
+                return None;
+            }
+            if self.cur.eq_kind(TokenKind::ParenR) {
+                josi = self.cur.next().josi; // skip ')'
+                break;
+            }
+            if self.cur.eq_kind(TokenKind::Comma) {
+                self.cur.next();
+                continue;
+            }
+            if !self.check_value() {
+                self.throw_error_token(&format!("『{}』の呼び出しで引数が読めません", word_t.value.to_string()), paren_t);
+                return None;
+            }
+            nodes.push(self.stack.pop().unwrap_or(Node::new_nop()));
+        }
+        Some(Node::new(NodeKind::CallFuncValue, NodeValue::NodeList(nodes), josi, self.pos(word_t)))
+    }
+}
+
+/// 代入記号("="と"+="などの複合代入)か
+fn is_let_flag(kind: TokenKind) -> bool {
+    matches!(kind, TokenKind::Eq | TokenKind::PlusEq | TokenKind::MinusEq | TokenKind::MulEq | TokenKind::DivEq)
 }
 
 pub fn tokenize_test(code: &str) -> Vec<Token> {
@@ -8,6 +8,10 @@ pub fn check_kind(s: &str) -> TokenKind {
     // Word => 予約語
     if s == "もし" { return TokenKind::If; }
     if s == "回" { return TokenKind::Kai; }
+    if s == "間" { return TokenKind::While; }
+    if s == "反復" { return TokenKind::Foreach; }
+    if s == "条件分岐" { return TokenKind::Switch; }
+    if s == "エラー監視" { return TokenKind::Try; }
     if s == "ここまで" { return TokenKind::BlockEnd; }
     if s == "ここから" { return TokenKind::BlockBegin; }
     if s == "かつ" { return TokenKind::And; }
@@ -18,6 +22,10 @@ pub fn check_kind(s: &str) -> TokenKind {
     if s == "繰返" { return TokenKind::For; }
     if s == "戻" { return TokenKind::Return; }
     if s == "変数" { return TokenKind::DefVar; }
+    if s == "定数" { return TokenKind::DefConst; }
+    if s == "関数" { return TokenKind::Func; }
+    if s == "増" { return TokenKind::Inc; }
+    if s == "減" { return TokenKind::Dec; }
     if s == "代入" { return TokenKind::Dainyu; }
     // todo
     TokenKind::Word
@@ -1,7 +1,10 @@
 //! インタプリタ Node を順に実行する
 // 走者 - Vec<Node>を順に実行
-use crate::{tokenizer, parser};
+use crate::{tokenizer, indent, parser};
+use std::{cell::RefCell, rc::Rc};
 use crate::node::*;
+use crate::decimal::Decimal;
+use crate::bigint::BigInt;
 use crate::sys_function_debug;
 use crate::sys_function;
 
@@ -11,11 +14,13 @@ pub fn run_node(ctx: &mut NodeContext, cur: &Node) -> Option<NodeValue> {
         NodeKind::Nop => {},
         NodeKind::Comment => {},
         NodeKind::LetVarGlobal => result = run_let(ctx, cur),
+        NodeKind::LetVarLocal => result = run_let(ctx, cur),
         NodeKind::Int => result = cur.value.clone(),
         NodeKind::Bool => result = cur.value.clone(),
         NodeKind::Number => result = cur.value.clone(),
         NodeKind::String => result = cur.value.clone(),
         NodeKind::GetVarGlobal => result = run_get_var(ctx, cur).unwrap_or(NodeValue::Empty),
+        NodeKind::GetVarLocal => result = run_get_var(ctx, cur).unwrap_or(NodeValue::Empty),
         NodeKind::Operator => result = run_operator(ctx, cur),
         NodeKind::CallSysFunc => result = run_call_sysfunc(ctx, cur),
         NodeKind::CallUserFunc => result = run_call_userfunc(ctx, cur),
@@ -27,6 +32,10 @@ pub fn run_node(ctx: &mut NodeContext, cur: &Node) -> Option<NodeValue> {
         },
         NodeKind::If => match run_if(ctx, cur) { Some(v) => result = v, None => {}},
         NodeKind::Kai => match run_kai(ctx, cur) { Some(v) => result = v, None => {}},
+        NodeKind::While => match run_while(ctx, cur) { Some(v) => result = v, None => {}},
+        NodeKind::Foreach => match run_foreach(ctx, cur) { Some(v) => result = v, None => {}},
+        NodeKind::Switch => match run_switch(ctx, cur) { Some(v) => result = v, None => {}},
+        NodeKind::Try => match run_try(ctx, cur) { Some(v) => result = v, None => {}},
         NodeKind::For => match run_for(ctx, cur) { Some(v) => result = v, None => {}},
         NodeKind::Break => { ctx.try_break = Some(ctx.callstack_level) },
         NodeKind::Continue => { ctx.try_continue = Some(ctx.callstack_level) },
@@ -34,12 +43,16 @@ pub fn run_node(ctx: &mut NodeContext, cur: &Node) -> Option<NodeValue> {
         NodeKind::ArrayCreate => result = run_array_create(ctx, cur),
         NodeKind::ArrayRef => result = run_array_ref(ctx, cur),
         NodeKind::ArrayLet => result = run_array_let(ctx, cur),
-        _ => { println!("[エラー] runner未実装のノード :{:?}", cur); return None; }
+        NodeKind::DictCreate => result = run_dict_create(ctx, cur),
+        NodeKind::FuncRef => result = cur.value.clone(),
+        NodeKind::CallFuncValue => result = run_call_func_value(ctx, cur),
+        NodeKind::CallMethod => result = run_call_method(ctx, cur),
+        NodeKind::RecordField => result = run_record_field(ctx, cur),
     }
     Some(result)
 }
 
-pub fn run_nodes(ctx: &mut NodeContext, nodes: &Vec<Node>) -> Result<NodeValue, String> {
+pub fn run_nodes(ctx: &mut NodeContext, nodes: &[Node]) -> Result<NodeValue, String> {
     ctx.callstack_level += 1;
     let nodes_len = nodes.len();
     let mut result = NodeValue::Empty;
@@ -57,6 +70,8 @@ pub fn run_nodes(ctx: &mut NodeContext, nodes: &Vec<Node>) -> Result<NodeValue,
         index += 1;
     }
     ctx.callstack_level -= 1;
+    // 最後の文でエラーがあった場合
+    if ctx.has_error() { return Err(ctx.get_error_str()); }
     Ok(result)
 }
 
@@ -76,68 +91,181 @@ pub fn run_return(ctx: &mut NodeContext, cur: &Node) -> NodeValue {
     }
 }
 
+/// 繰り返し本文の実行後に「抜ける」「続ける」「戻る」を処理する (繰り返しを終えるならtrue)
+fn check_loop_exit(ctx: &mut NodeContext) -> bool {
+    // 抜けるの処理
+    if ctx.try_break.is_some() {
+        ctx.try_break = None;
+        return true;
+    }
+    // 続けるの処理
+    if ctx.try_continue.is_some() {
+        ctx.try_continue = None;
+        return false;
+    }
+    // 戻るの処理
+    ctx.try_return.is_some()
+}
+
+/// 繰り返しで使う変数(回数や対象など)の値を退避する (外側の繰り返しが同じ変数を使っていなければNone)
+fn save_loop_vars(ctx: &mut NodeContext, names: &[&'static str]) -> Vec<Option<NodeValue>> {
+    let local = ctx.scopes.scopes.last().unwrap();
+    let saved = names.iter()
+        .map(|name| if ctx.loop_vars.contains(name) { Some(local.get_var(name)) } else { None })
+        .collect();
+    ctx.loop_vars.extend(names);
+    saved
+}
+
+/// 退避した繰り返し用の変数を元に戻す (入れ子の繰り返しで外側の値が上書きされないように)
+/// 外側の繰り返しがなければ、繰り返しの後も最後の値が残る
+fn restore_loop_vars(ctx: &mut NodeContext, names: &[&'static str], values: Vec<Option<NodeValue>>) {
+    let len = ctx.loop_vars.len().saturating_sub(names.len());
+    ctx.loop_vars.truncate(len);
+    for (name, value) in names.iter().zip(values) {
+        if let Some(value) = value { ctx.scopes.set_value_local_scope(name, value); }
+    }
+}
+
 pub fn run_for(ctx: &mut NodeContext, cur: &Node) -> Option<NodeValue> {
     let nodes = cur.value.to_nodes();
     let loop_node = &nodes[0];
     let kara_node = &nodes[1];
     let made_node = &nodes[2];
-    let body_node = &nodes[3];
-    let kara_v = run_node(ctx, &kara_node).unwrap_or(NodeValue::Empty);
-    let made_v = run_node(ctx, &made_node).unwrap_or(NodeValue::Empty);
+    let step_node = &nodes[3];
+    let body_node = &nodes[4];
+    let kara_v = for_number(run_node(ctx, kara_node).unwrap_or(NodeValue::Empty));
+    let made_v = for_number(run_node(ctx, made_node).unwrap_or(NodeValue::Empty));
+    // 増分(省略時は1)
+    let step_v = if step_node.kind == NodeKind::Nop {
+        NodeValue::I(1)
+    } else {
+        for_number(run_node(ctx, step_node).unwrap_or(NodeValue::Empty))
+    };
+    let step_f = step_v.to_float(0.0);
+    if step_f == 0.0 || !step_f.is_finite() {
+        ctx.throw_runtime_error(format!("『繰り返す』の増分『{}』が正しくありません。", step_v.to_string()), cur.pos);
+        return None;
+    }
     let mut result = None;
-    for i in kara_v.to_int(0)..=made_v.to_int(0) {
-        if loop_node.kind == NodeKind::GetVarGlobal {
-            match &loop_node.value {
-                NodeValue::GetVar(info) => {
-                    let name: String = info.name.clone();
-                    ctx.scopes.set_value_local_scope(&name, NodeValue::I(i));
-                },
-                _ => {},
-            }
+    let mut n = 0;
+    while let Some(v) = for_value_at(&kara_v, &made_v, &step_v, n) {
+        if let NodeValue::GetVar(info) = &loop_node.value {
+            ctx.set_var_value(info, v);
         }
         result = run_node(ctx, body_node);
-        // 抜けるの処理
-        if ctx.try_break != None {
-            ctx.try_break = None;
-            break;
-        }
-        // 続けるの処理
-        if ctx.try_continue != None {
-            ctx.try_continue = None;
-            continue;
-        }
-        // 戻るの処理
-        if ctx.try_return != None {
-            break;
-        }
+        if check_loop_exit(ctx) { break; }
+        n += 1;
     }
     result
 }
 
+/// 『繰り返す』で数える値 ... 文字列は整数か小数に変換する
+fn for_number(v: NodeValue) -> NodeValue {
+    match &v {
+        NodeValue::S(s) => match BigInt::parse(s.trim()) {
+            Some(n) => NodeValue::from_bigint(n),
+            None => NodeValue::F(v.to_float(0.0)),
+        },
+        _ => v,
+    }
+}
+
+/// 『繰り返す』のn回目の値を返す (範囲を超えたらNone)
+/// 増分の符号は無視して「から」と「まで」の大小で向きを決める。小数が含まれていれば小数で数える
+fn for_value_at(kara_v: &NodeValue, made_v: &NodeValue, step_v: &NodeValue, n: isize) -> Option<NodeValue> {
+    let is_float = [kara_v, made_v, step_v].iter().any(|v| matches!(v, NodeValue::F(_)));
+    if is_float {
+        let (kara, made) = (kara_v.to_float(0.0), made_v.to_float(0.0));
+        let step = if kara <= made { step_v.to_float(0.0).abs() } else { -step_v.to_float(0.0).abs() };
+        let v = kara + step * n as f64;
+        let eps = step.abs() * 1e-9; // 計算誤差の分だけ範囲を広げる
+        if (step > 0.0 && v > made + eps) || (step < 0.0 && v < made - eps) { return None; }
+        return Some(NodeValue::F(v));
+    }
+    // 10進数の小数を含むなら誤差なく数える
+    if [kara_v, made_v, step_v].iter().any(|v| matches!(v, NodeValue::Decimal(_))) {
+        let (kara, made, step) = (kara_v.to_decimal()?, made_v.to_decimal()?, step_v.to_decimal()?);
+        let step = if (step < Decimal::from_isize(0)) == (kara <= made) { Decimal::from_isize(0).checked_sub(&step)? } else { step };
+        let v = step.checked_mul(&Decimal::from_isize(n)).and_then(|d| kara.checked_add(&d))?;
+        if (kara <= made && v > made) || (kara > made && v < made) { return None; }
+        return Some(NodeValue::Decimal(v));
+    }
+    // isizeに収まらない整数を含むなら任意精度の整数で数える
+    if [kara_v, made_v, step_v].iter().any(|v| matches!(v, NodeValue::BigInt(_))) {
+        let (kara, made, step) = (kara_v.to_bigint()?, made_v.to_bigint()?, step_v.to_bigint()?);
+        let step = if step.is_negative() == (kara <= made) { step.neg() } else { step };
+        let v = kara.add(&step.mul(&BigInt::from_isize(n)));
+        if (kara <= made && v > made) || (kara > made && v < made) { return None; }
+        return Some(NodeValue::from_bigint(v));
+    }
+    let (kara, made) = (kara_v.to_int(0), made_v.to_int(0));
+    let step = if kara <= made { step_v.to_int(0).abs() } else { -step_v.to_int(0).abs() };
+    let v = step.checked_mul(n).and_then(|d| kara.checked_add(d))?;
+    if (step > 0 && v > made) || (step < 0 && v < made) { return None; }
+    Some(NodeValue::I(v))
+}
+
 pub fn run_kai(ctx: &mut NodeContext, cur: &Node) -> Option<NodeValue> {
     let nodes = cur.value.to_nodes();
     let kaisu_node = &nodes[0];
     let body_node = &nodes[1];
     let kaisu = run_node(ctx, kaisu_node).unwrap_or(NodeValue::I(0));
     let mut result = None;
+    let loop_vars = ["回数"];
+    let saved = save_loop_vars(ctx, &loop_vars);
     for i in 0..kaisu.to_int(0) {
-        ctx.scopes.set_value(1, "回数", NodeValue::I(i + 1));
+        ctx.scopes.set_value_local_scope("回数", NodeValue::I(i + 1));
         result = run_node(ctx, body_node);
-        // 抜けるの処理
-        if ctx.try_break != None {
-            ctx.try_break = None;
-            break;
-        }
-        // 続けるの処理
-        if ctx.try_continue != None {
-            ctx.try_continue = None;
-            continue;
-        }
-        // 戻るの処理
-        if ctx.try_return != None {
-            break;
+        if check_loop_exit(ctx) { break; }
+    }
+    restore_loop_vars(ctx, &loop_vars, saved);
+    result
+}
+
+pub fn run_while(ctx: &mut NodeContext, cur: &Node) -> Option<NodeValue> {
+    let nodes = cur.value.to_nodes();
+    let cond_node = &nodes[0];
+    let body_node = &nodes[1];
+    let mut result = None;
+    // 条件を評価しながら繰り返す
+    while let Some(cond) = run_node(ctx, cond_node) {
+        if ctx.has_error() || !cond.to_bool() { break; }
+        result = run_node(ctx, body_node);
+        if check_loop_exit(ctx) { break; }
+    }
+    result
+}
+
+pub fn run_foreach(ctx: &mut NodeContext, cur: &Node) -> Option<NodeValue> {
+    let nodes = cur.value.to_nodes();
+    let target_node = &nodes[0];
+    let body_node = &nodes[1];
+    let target = run_node(ctx, target_node).unwrap_or(NodeValue::Empty);
+    // (対象キー, 対象)の一覧を作る
+    let items: Vec<(NodeValue, NodeValue)> = match target {
+        NodeValue::Empty => vec![],
+        NodeValue::A(list) => list.into_iter().enumerate()
+            .map(|(i, v)| (NodeValue::I(i as isize), v))
+            .collect(),
+        NodeValue::H(dict) | NodeValue::R(_, dict) => dict.iter()
+            .map(|(k, v)| (NodeValue::S(k.clone()), v.clone()))
+            .collect(),
+        _ => {
+            ctx.throw_runtime_error(format!("『反復』の対象『{}』は配列ではありません。", target.to_string()), cur.pos);
+            return None;
         }
+    };
+    let mut result = None;
+    let loop_vars = ["対象", "対象キー"];
+    let saved = save_loop_vars(ctx, &loop_vars);
+    for (key, value) in items.into_iter() {
+        ctx.scopes.set_value_local_scope("対象キー", key);
+        ctx.scopes.set_value_local_scope("対象", value);
+        result = run_node(ctx, body_node);
+        if check_loop_exit(ctx) { break; }
     }
+    restore_loop_vars(ctx, &loop_vars, saved);
     result
 }
 
@@ -158,12 +286,46 @@ pub fn run_if(ctx: &mut NodeContext, cur: &Node) -> Option<NodeValue> {
     }
 }
 
+pub fn run_switch(ctx: &mut NodeContext, cur: &Node) -> Option<NodeValue> {
+    let nodes = cur.value.to_nodes();
+    let target = run_node(ctx, &nodes[0])?;
+    let else_node = &nodes[1];
+    // 最初に一致した条件だけを実行する
+    for pair in nodes[2..].chunks(2) {
+        let cond = run_node(ctx, &pair[0])?;
+        if NodeValue::calc_eq(&target, &cond).to_bool() {
+            return run_node(ctx, &pair[1]);
+        }
+    }
+    run_node(ctx, else_node)
+}
+
+pub fn run_try(ctx: &mut NodeContext, cur: &Node) -> Option<NodeValue> {
+    let nodes = cur.value.to_nodes();
+    let try_node = &nodes[0];
+    let catch_node = &nodes[1];
+    let error_index = ctx.get_error_len();
+    let result = run_node(ctx, try_node);
+    if !ctx.has_error() { return result; }
+    // 監視中に起きたエラーを取り除く (最初のエラーが発生元)
+    let errors = ctx.pop_errors(error_index);
+    let err = match errors.into_iter().next() {
+        Some(e) => e,
+        None => return result,
+    };
+    ctx.scopes.set_value_local_scope("エラーメッセージ", NodeValue::S(err.message));
+    ctx.scopes.set_value_local_scope("エラーファイル", NodeValue::S(err.filename));
+    ctx.scopes.set_value_local_scope("エラー行", NodeValue::I(err.lineno as isize));
+    ctx.scopes.set_value_local_scope("エラー列", NodeValue::I(err.colno as isize));
+    run_node(ctx, catch_node)
+}
+
 fn run_call_sysfunc(ctx: &mut NodeContext, node: &Node) -> NodeValue {
     let mut args: Vec<NodeValue> = vec![];
     let func_no = match &node.value {
         NodeValue::CallFunc(func_name, no, nodes) => {
             for n in nodes.iter() {
-                let v = match run_nodes(ctx, &vec![n.clone()]) {
+                let v = match run_nodes(ctx, std::slice::from_ref(n)) {
                     Ok(v) => v,
                     Err(err) => {
                         ctx.throw_error(
@@ -179,8 +341,22 @@ fn run_call_sysfunc(ctx: &mut NodeContext, node: &Node) -> NodeValue {
         }
         _ => return NodeValue::Empty,
     };
-    let info:&SysFuncInfo = &ctx.sysfuncs[func_no];
-    let result = (info.func)(ctx, args);
+    call_sysfunc(ctx, func_no, args, node.pos)
+}
+
+/// システム関数(func_no)を実行する
+fn call_sysfunc(ctx: &mut NodeContext, func_no: usize, args: Vec<NodeValue>, pos: NodePos) -> NodeValue {
+    ctx.sysfunc_pos = pos;
+    let info = &ctx.sysfuncs[func_no];
+    // 引数が足りなければ関数を呼ばずにエラーにする
+    if args.len() < info.args_len {
+        let msg = format!("『{}』の引数が足りません。{}個必要ですが{}個でした。", info.name, info.args_len, args.len());
+        ctx.throw_runtime_error(msg, pos);
+        return NodeValue::Empty;
+    }
+    // 関数がコンテキストを書き換えてもよいように参照を複製してから呼ぶ
+    let func = Rc::clone(&info.func);
+    let result = func(ctx, args);
     match result {
         Some(value) => {
             ctx.scopes.set_value_local_scope("それ", value.clone());
@@ -196,16 +372,152 @@ fn run_call_userfunc(ctx: &mut NodeContext, node: &Node) -> NodeValue {
         NodeValue::CallFunc(func_name, no, nodes) => (func_name, *no, nodes),
         _ => return NodeValue::Empty,
     };
-    // 関数を得る
-    let info = NodeVarInfo { level: 1, no: func_no, name: func_name.clone() };
+    let mut linked: Vec<NodeVarInfo> = vec![];
+    let result = match run_userfunc_args(ctx, func_name, arg_nodes, node.pos, &mut linked) {
+        Some(args) => {
+            let info = NodeVarInfo { level: 1, no: func_no, name: func_name.clone() };
+            call_userfunc(ctx, &info, args, node.pos)
+        },
+        None => NodeValue::Empty,
+    };
+    unlink_var_args(ctx, &linked);
+    result
+}
+
+/// ユーザー関数の引数を評価する (配列と辞書の変数は参照渡しにするのでlinkedに記録する)
+fn run_userfunc_args(ctx: &mut NodeContext, func_name: &str, arg_nodes: &[Node], pos: NodePos, linked: &mut Vec<NodeVarInfo>) -> Option<Vec<NodeValue>> {
+    let mut args: Vec<NodeValue> = vec![];
+    for n in arg_nodes.iter() {
+        if let Some(link) = link_var_arg(ctx, n, linked) {
+            args.push(link);
+            continue;
+        }
+        match run_nodes(ctx, std::slice::from_ref(n)) {
+            Ok(val) => args.push(val),
+            Err(err) => {
+                ctx.throw_error(
+                    NodeErrorKind::RuntimeError, NodeErrorLevel::Error, 
+                    format!("『{}』の呼び出しでエラー。{}", func_name, err), 
+                    pos);
+                return None;
+            }
+        };
+    }
+    Some(args)
+}
+
+/// メソッド(値)の(メソッド名)を呼び出す ... 最初の引数(自身)の型名から『型名の(メソッド名)』を探す
+fn run_call_method(ctx: &mut NodeContext, node: &Node) -> NodeValue {
+    let (method_name, arg_nodes) = match &node.value {
+        NodeValue::CallFunc(name, _, nodes) => (name, nodes),
+        _ => return NodeValue::Empty,
+    };
+    let mut linked: Vec<NodeVarInfo> = vec![];
+    let result = match run_userfunc_args(ctx, method_name, arg_nodes, node.pos, &mut linked) {
+        Some(args) => {
+            let target = args.first().map(|v| v.get_link_value()).unwrap_or(NodeValue::Empty);
+            let method = match &target {
+                NodeValue::R(type_name, _) => format!("{}の{}", type_name, method_name),
+                _ => String::new(),
+            };
+            // 型のメソッドがなければ同じ名前の関数を呼ぶ
+            let global = &ctx.scopes.scopes[1];
+            let func_name = [method, method_name.clone()].into_iter().find(|name| match global.var_names.get(name) {
+                Some(no) => matches!(global.var_metas[*no].kind, NodeVarKind::UserFunc(_)),
+                None => false,
+            });
+            match func_name.and_then(|name| global.var_names.get(&name).map(|no| (name, *no))) {
+                Some((name, no)) => {
+                    let info = NodeVarInfo { level: 1, no, name };
+                    call_userfunc(ctx, &info, args, node.pos)
+                },
+                None => {
+                    ctx.throw_runtime_error(format!("『{}』にはメソッド『{}』がありません。", target.to_string(), method_name), node.pos);
+                    NodeValue::Empty
+                }
+            }
+        },
+        None => NodeValue::Empty,
+    };
+    unlink_var_args(ctx, &linked);
+    result
+}
+
+/// 型の値のフィールドを参照する (フィールドを持つ型の値でなければ同じ名前の関数を呼ぶ)
+fn run_record_field(ctx: &mut NodeContext, node: &Node) -> NodeValue {
+    let nodes = node.value.to_nodes();
+    let target = run_node(ctx, &nodes[0]).unwrap_or(NodeValue::Empty).get_link_value();
+    let key = nodes[1].value.to_string();
+    if let NodeValue::R(_, dict) = &target {
+        if let Some(v) = dict.get(&key) { return v.clone(); }
+    }
+    let func = run_node(ctx, &nodes[2]).unwrap_or(NodeValue::Empty);
+    call_func_value(ctx, &func, vec![target], node.pos)
+}
+
+/// 型(info)の値をフィールドの値argsから作る (省略したフィールドは既定値)
+fn new_record(ctx: &mut NodeContext, info: &NodeVarInfo, fields: &[SysArg], args: Vec<NodeValue>, pos: NodePos) -> NodeValue {
+    if args.len() > fields.len() {
+        ctx.throw_runtime_error(format!("型『{}』の値が多すぎます。", info.name), pos);
+        return NodeValue::Empty;
+    }
+    let mut dict = NodeDict::new();
+    for (i, field) in fields.iter().enumerate() {
+        let value = match (args.get(i), &field.default) {
+            (Some(v), _) => v.get_link_value(),
+            (None, Some(node)) => run_node(ctx, node).unwrap_or(NodeValue::Empty),
+            (None, None) => NodeValue::Empty,
+        };
+        dict.set(&field.name, value);
+    }
+    NodeValue::R(info.name.clone(), dict)
+}
+
+/// 引数が配列か辞書の変数であれば、変数の値をリンク(NodeValue::Link)に置き換えて同じリンクを返す
+/// (関数の中で要素を変更すると呼び出し元の変数にも反映される。呼び出し後にunlink_var_argsで戻す)
+/// 定数はリンクせずに値を複製して渡す
+fn link_var_arg(ctx: &mut NodeContext, node: &Node, linked: &mut Vec<NodeVarInfo>) -> Option<NodeValue> {
+    if node.kind != NodeKind::GetVarGlobal && node.kind != NodeKind::GetVarLocal { return None; }
+    let info = match &node.value {
+        NodeValue::GetVar(info) => info,
+        _ => return None,
+    };
+    if ctx.is_read_only(info) { return None; }
+    let var = ctx.get_var_value_mut(info)?;
+    match var {
+        // 既にリンクなら(参照渡しされた引数をさらに渡す場合)そのまま渡す
+        NodeValue::Link(link) => Some(NodeValue::Link(link.clone())),
+        NodeValue::A(_) | NodeValue::H(_) | NodeValue::R(_, _) => {
+            let link = Rc::new(RefCell::new(std::mem::replace(var, NodeValue::Empty)));
+            *var = NodeValue::Link(link.clone());
+            linked.push(info.clone());
+            Some(NodeValue::Link(link))
+        },
+        _ => None,
+    }
+}
+
+/// link_var_argでリンクにした変数を元の値に戻す
+fn unlink_var_args(ctx: &mut NodeContext, linked: &[NodeVarInfo]) {
+    for info in linked.iter() {
+        if let Some(var) = ctx.get_var_value_mut(info) {
+            if let NodeValue::Link(_) = var {
+                *var = var.get_link_value();
+            }
+        }
+    }
+}
+
+/// ユーザー関数(グローバル領域の変数info)を引数argsで実行する
+fn call_userfunc(ctx: &mut NodeContext, info: &NodeVarInfo, args: Vec<NodeValue>, pos: NodePos) -> NodeValue {
     // println!("@@context:{:?}", ctx.scopes.scopes[1]);
     // println!("@@本体:info={:?}", info);
-    let func_value = match ctx.scopes.get_var_value(&info) { // 関数本体
+    let func_value = match ctx.scopes.get_var_value(info) { // 関数本体
         Some(v) => v,
         None => return NodeValue::Empty,
     };
     // println!("@@本体:{}", func_value.to_string());
-    let meta = match ctx.scopes.get_var_meta(&info) { // メタ情報
+    let meta = match ctx.scopes.get_var_meta(info) { // メタ情報
         Some(v) => v,
         None => return NodeValue::Empty,
     };
@@ -216,35 +528,41 @@ fn run_call_userfunc(ctx: &mut NodeContext, node: &Node) -> NodeValue {
         _ => return NodeValue::Empty,
     };
     // println!("@@args");
-    // 関数スコープを作り、ローカル変数を登録する
-    let mut scope = NodeScope::new();
-    // 関数の引数を得る
-    for (i, n) in arg_nodes.iter().enumerate() {
-        match run_nodes(ctx, &vec![n.clone()]) {
-            Ok(val) => {
-                let name = &func_args[i].name;
-                scope.set_var(name, val);
-            },
-            Err(err) => {
-                ctx.throw_error(
-                    NodeErrorKind::RuntimeError, NodeErrorLevel::Error, 
-                    format!("『{}』の呼び出しでエラー。{}", func_name, err), 
-                    node.pos);
-                return NodeValue::Empty;
-            }
+    if args.len() > func_args.len() {
+        ctx.throw_runtime_error(format!("関数『{}』の引数が多すぎます。", info.name), pos);
+        return NodeValue::Empty;
+    }
+    // 省略された引数は既定値を使う
+    let mut args = args;
+    for arg in func_args[args.len()..].iter() {
+        let value = match &arg.default {
+            Some(node) => run_node(ctx, node).unwrap_or(NodeValue::Empty),
+            None => NodeValue::Empty,
         };
+        args.push(value);
+    }
+    // 関数スコープを作り、ローカル変数を登録する (変数番号は構文解析時と同じ)
+    let mut scope = NodeScope::from_names(&meta.frame);
+    for no in meta.frame_read_only.iter() {
+        if let Some(m) = scope.var_metas.get_mut(*no) { m.read_only = true; }
+    }
+    // 関数の引数を設定
+    for (arg, val) in func_args.iter().zip(args) {
+        scope.set_var(&arg.name, val);
     }
     // 関数を実行
     ctx.scopes.push_local(scope);
     let tmp_return_level = ctx.return_level;
     ctx.return_level = ctx.callstack_level;
+    // 関数の中の繰り返しは呼び出し元の繰り返しとは別の変数を使う
+    let tmp_loop_vars = std::mem::take(&mut ctx.loop_vars);
     match func_value {
         NodeValue::CallFunc(name, _no, nodes) => {
             // println!("@@@CALL:{}", nodes_to_string(&nodes, "\n"));
             match run_nodes(ctx, &nodes) {
                 Ok(v) => v,
                 Err(e) => {
-                    ctx.throw_runtime_error(format!("『{}』の呼び出しでエラー。{}", name, e), node.pos);
+                    ctx.throw_runtime_error(format!("『{}』の呼び出しでエラー。{}", name, e), pos);
                     NodeValue::Empty
                 }
             };
@@ -256,12 +574,65 @@ fn run_call_userfunc(ctx: &mut NodeContext, node: &Node) -> NodeValue {
         ctx.try_return = None;
     }
     ctx.return_level = tmp_return_level;
-    let result = func_scope.get_var("それ");
+    ctx.loop_vars = tmp_loop_vars;
+    // 参照渡しされた引数を返す場合はリンクを外して値を返す
+    let result = func_scope.get_var("それ").get_link_value();
     // println!("*** 関数のスコープ={:?}", func_scope);
     ctx.scopes.set_value_local_scope("それ", result.clone());
     result
 }
 
+/// 関数の値(NodeValue::Func)を引数argsで呼び出す
+pub fn call_func_value(ctx: &mut NodeContext, func: &NodeValue, mut args: Vec<NodeValue>, pos: NodePos) -> NodeValue {
+    let info = match func {
+        NodeValue::Func(info) => info,
+        _ => {
+            ctx.throw_runtime_error(format!("『{}』は関数ではないので呼び出せません。", func.to_string()), pos);
+            return NodeValue::Empty;
+        }
+    };
+    let kind = ctx.get_var_meta(info).map(|meta| meta.kind);
+    match (kind, ctx.get_var_value(info)) {
+        (Some(NodeVarKind::SysFunc(sys_args)), Some(NodeValue::CallFunc(_, func_no, _))) => {
+            if args.len() > sys_args.len() {
+                ctx.throw_runtime_error(format!("関数『{}』の引数が多すぎます。", info.name), pos);
+                return NodeValue::Empty;
+            }
+            // 足りない引数は空で補う
+            args.resize(sys_args.len(), NodeValue::Empty);
+            let args = args.iter().map(|v| v.get_link_value()).collect();
+            call_sysfunc(ctx, func_no, args, pos)
+        },
+        (Some(NodeVarKind::UserFunc(_)), _) => call_userfunc(ctx, info, args, pos),
+        (Some(NodeVarKind::Type(fields)), _) => new_record(ctx, info, &fields, args, pos),
+        _ => {
+            ctx.throw_runtime_error(format!("『{}』は関数ではないので呼び出せません。", info.name), pos);
+            NodeValue::Empty
+        }
+    }
+}
+
+fn run_call_func_value(ctx: &mut NodeContext, node: &Node) -> NodeValue {
+    let nodes = node.value.to_nodes();
+    let func = run_node(ctx, &nodes[0]).unwrap_or(NodeValue::Empty);
+    let mut args: Vec<NodeValue> = vec![];
+    let mut linked: Vec<NodeVarInfo> = vec![];
+    for n in nodes[1..].iter() {
+        if let Some(link) = link_var_arg(ctx, n, &mut linked) {
+            args.push(link);
+            continue;
+        }
+        args.push(run_node(ctx, n).unwrap_or(NodeValue::Empty));
+        if ctx.has_error() {
+            unlink_var_args(ctx, &linked);
+            return NodeValue::Empty;
+        }
+    }
+    let result = call_func_value(ctx, &func, args, node.pos);
+    unlink_var_args(ctx, &linked);
+    result
+}
+
 fn run_let(ctx: &mut NodeContext, node: &Node) -> NodeValue {
     let let_value: &NodeValueParamLet = match &node.value {
         NodeValue::LetVar(ref let_value) => let_value,
@@ -269,11 +640,8 @@ fn run_let(ctx: &mut NodeContext, node: &Node) -> NodeValue {
     };
     let value_node:&Vec<Node> = &let_value.value_node;
     let value = run_nodes(ctx, value_node).unwrap_or(NodeValue::Empty);
-    let info = let_value.var_info.clone();
-    // let level = info.level;
-    let name = &info.name;
-    // println!("- let {}:L{}={:?}", name, level, value);
-    ctx.scopes.set_value(info.level, name, value.clone());
+    // println!("- let {:?}={:?}", let_value.var_info, value);
+    ctx.set_var_value(&let_value.var_info, value.clone());
     value
 }
 
@@ -292,28 +660,45 @@ fn run_operator(ctx: &mut NodeContext, node: &Node) -> NodeValue {
         NodeValue::Operator(op) => op,
         _ => return NodeValue::Empty,
     };
-    let right = run_nodes(ctx, &vec![op.nodes[1].clone()]).unwrap_or(NodeValue::Empty);
-    let left = run_nodes(ctx, &vec![op.nodes[0].clone()]).unwrap_or(NodeValue::Empty);
-    match op.flag {
-        '(' => left,
+    let right = run_nodes(ctx, &op.nodes[1..=1]).unwrap_or(NodeValue::Empty);
+    let left = run_nodes(ctx, &op.nodes[0..=0]).unwrap_or(NodeValue::Empty);
+    if op.flag == '(' { return left; }
+    let result = calc_operator(op.flag, &left, &right);
+    // 10進数の小数の計算で桁あふれしたら、誤差のある小数にせずエラーにする (0で割った場合は除く)
+    if matches!(op.flag, '+' | '-' | '*' | '/' | '%') && NodeValue::is_decimal_pair(&left, &right)
+        && !matches!(result, NodeValue::Decimal(_))
+        && !(matches!(op.flag, '/' | '%') && right.to_decimal().is_some_and(|d| d.is_zero())) {
+        ctx.throw_runtime_error(format!("10進数の小数の計算『{}{}{}』で桁あふれしました。", left.to_string(), op.flag, right.to_string()), node.pos);
+        return NodeValue::Empty;
+    }
+    // 数値に変換できない文字列に整数を足そうとした
+    if op.flag == '+' && matches!((&left, &right, &result), (NodeValue::S(_), NodeValue::I(_), NodeValue::Empty)) {
+        ctx.throw_runtime_error(format!("文字列『{}』を数値に変換できないので『{}』を足せません。", left.to_string(), right.to_string()), node.pos);
+        return NodeValue::Empty;
+    }
+    result
+}
+
+fn calc_operator(flag: char, left: &NodeValue, right: &NodeValue) -> NodeValue {
+    match flag {
         '!' => NodeValue::B(!left.to_bool()),
-        '+' => NodeValue::calc_plus(&left, &right),
-        '結' => NodeValue::calc_plus_str(&left, &right), // 文字列加算
-        '|' => NodeValue::calc_or(&left, &right), // または
-        '&' => NodeValue::calc_and(&left, &right), // かつ
-        '-' => NodeValue::calc_minus(&left, &right),
-        '*' => NodeValue::calc_mul(&left, &right),
-        '/' => NodeValue::calc_div(&left, &right),
-        '%' => NodeValue::calc_mod(&left, &right),
-        '=' => NodeValue::calc_eq(&left, &right),
-        '≠' => NodeValue::calc_noteq(&left, &right),
-        '>' => NodeValue::calc_gt(&left, &right),
-        '≧' => NodeValue::calc_gteq(&left, &right),
-        '<' => NodeValue::calc_lt(&left, &right),
-        '≦' => NodeValue::calc_lteq(&left, &right),
-        '^' => NodeValue::calc_pow(&left, &right),
+        '+' => NodeValue::calc_plus(left, right),
+        '結' => NodeValue::calc_plus_str(left, right), // 文字列加算
+        '|' => NodeValue::calc_or(left, right), // または
+        '&' => NodeValue::calc_and(left, right), // かつ
+        '-' => NodeValue::calc_minus(left, right),
+        '*' => NodeValue::calc_mul(left, right),
+        '/' => NodeValue::calc_div(left, right),
+        '%' => NodeValue::calc_mod(left, right),
+        '=' => NodeValue::calc_eq(left, right),
+        '≠' => NodeValue::calc_noteq(left, right),
+        '>' => NodeValue::calc_gt(left, right),
+        '≧' => NodeValue::calc_gteq(left, right),
+        '<' => NodeValue::calc_lt(left, right),
+        '≦' => NodeValue::calc_lteq(left, right),
+        '^' => NodeValue::calc_pow(left, right),
         _ => {
-            println!("[実行時エラー]未実装の演算子記号:『{}』", op.flag);
+            println!("[実行時エラー]未実装の演算子記号:『{}』", flag);
             NodeValue::Empty
         },
     }
@@ -336,6 +721,17 @@ fn run_array_create(ctx: &mut NodeContext, node: &Node) -> NodeValue {
     NodeValue::A(val_array)
 }
 
+fn run_dict_create(ctx: &mut NodeContext, node: &Node) -> NodeValue {
+    let mut dict = NodeDict::new();
+    let nlist = node.value.to_nodes();
+    for pair in nlist.chunks(2) {
+        let key = pair[0].value.to_string();
+        let value = run_node(ctx, &pair[1]).unwrap_or(NodeValue::Empty);
+        dict.set(&key, value);
+    }
+    NodeValue::H(dict)
+}
+
 fn run_array_let(ctx: &mut NodeContext, node: &Node) -> NodeValue {
     // 配列要素への代入
     let param_let = match &node.value {
@@ -343,9 +739,8 @@ fn run_array_let(ctx: &mut NodeContext, node: &Node) -> NodeValue {
         _ => { return NodeValue::Empty; }
     };
     let name = param_let.var_info.clone().name;
-    let mut index_list: Vec<usize> = vec![];
+    let mut index_list: Vec<NodeValue> = vec![];
     let let_value: NodeValue;
-    let mut var: &mut NodeValue;
     {
         // 値を評価
         let value_node = &param_let.value_node;
@@ -362,52 +757,64 @@ fn run_array_let(ctx: &mut NodeContext, node: &Node) -> NodeValue {
         let index_vec: &Vec<Node> = &param_let.index_node;
         for index_node in index_vec.iter() {
             let index = match run_node(ctx, index_node) {
-                Some(v) => v.to_int(0),
+                Some(v) => v,
                 None => {
                     let msg = format!("配列変数『{}』の{}番目の要素番号が取得できません。", name, index_no + 1);
                     ctx.throw_runtime_error(msg, node.pos);
                     return NodeValue::Empty;
                 }
             };
-            index_list.push(index as usize);
+            index_list.push(index);
             index_no += 1;
         }
     }
-    {
-        // 変数を得る
-        var = match ctx.scopes.get_var_value_mut(&param_let.var_info) {
-            None => {
-                let msg = format!("初期化されていない配列変数『{}』に代入しようとしました。", name);
-                ctx.throw_runtime_error(msg, node.pos);
-                return NodeValue::Empty;
-            },
-            Some(v) => v,
-        };
+    // 変数を得る
+    let var = match ctx.get_var_value_mut(&param_let.var_info) {
+        None => {
+            let msg = format!("初期化されていない配列変数『{}』に代入しようとしました。", name);
+            ctx.throw_runtime_error(msg, node.pos);
+            return NodeValue::Empty;
+        },
+        Some(v) => v,
+    };
+    // 参照渡しされた配列ならリンク先の値を書き換える
+    let result = match var {
+        NodeValue::Link(link) => {
+            let link = link.clone();
+            let mut link_value = link.borrow_mut();
+            set_array_value(&mut link_value, &index_list, let_value, &name)
+        },
+        _ => set_array_value(var, &index_list, let_value, &name),
+    };
+    match result {
+        Ok(v) => v,
+        Err(msg) => {
+            ctx.throw_runtime_error(msg, node.pos);
+            NodeValue::Empty
+        }
     }
-    {
-        for (i, index_p) in index_list.iter().enumerate() {
-            let index = *index_p;
-            // last?
-            if i == index_list.len() - 1 {
-                if !var.set_array_index(index as usize, let_value) {
-                    let msg = format!("配列変数『{}』の代入に失敗しました。", name);
-                    ctx.throw_runtime_error(msg, node.pos);
-                    return NodeValue::Empty;
-                }
-                return var.clone();
-            } else {
-                var = match var.get_array_index_mut(index) {
-                    Some(v) => v,
-                    None => {
-                        let msg = format!("配列変数『{}』の{}番目の要素が取得できませんでした。", name, i + 1);
-                        ctx.throw_runtime_error(msg, node.pos);
-                        return NodeValue::Empty;
-                    }
+}
+
+/// 配列変数varの要素(index_list)に値を設定する
+fn set_array_value(var: &mut NodeValue, index_list: &[NodeValue], let_value: NodeValue, name: &str) -> Result<NodeValue, String> {
+    let mut var = var;
+    for (i, index) in index_list.iter().enumerate() {
+        // last?
+        if i == index_list.len() - 1 {
+            if !var.set_index(index, let_value) {
+                return Err(format!("配列変数『{}』の代入に失敗しました。", name));
+            }
+            return Ok(var.clone());
+        } else {
+            var = match var.get_index_mut(index) {
+                Some(v) => v,
+                None => {
+                    return Err(format!("配列変数『{}』の{}番目の要素が取得できませんでした。", name, i + 1));
                 }
             }
         }
     }
-    NodeValue::Empty
+    Ok(NodeValue::Empty)
 }
 
 fn run_array_ref(ctx: &mut NodeContext, node: &Node) -> NodeValue {
@@ -435,7 +842,7 @@ fn run_array_ref(ctx: &mut NodeContext, node: &Node) -> NodeValue {
                     Some(index_val) => {
                         // todo: 配列の範囲エラーをチェックする
                         // println!("@@@array_ref.index={:?}", index_val);
-                        val = val.get_array_index(index_val.to_int(0) as usize).unwrap_or(NodeValue::Empty);
+                        val = val.get_index(&index_val).unwrap_or(NodeValue::Empty);
                         // println!("@@@array_ref.val={:?}", val);
                     },
                     None => {
@@ -459,16 +866,18 @@ pub struct RunOption {
     pub use_sysfunc: bool,
     pub debug: bool,
     pub return_print_log: bool,
+    /// 小数を10進数の小数として扱う (『!小数モード』と同じ)
+    pub decimal: bool,
 }
 impl RunOption {
     pub fn normal() -> Self {
-        Self { use_sysfunc: true, debug: false, return_print_log: false }
+        Self { use_sysfunc: true, debug: false, return_print_log: false, decimal: false }
     }
     pub fn simple() -> Self {
-        Self { use_sysfunc: false, debug: true, return_print_log: false }
+        Self { use_sysfunc: false, debug: true, return_print_log: false, decimal: false }
     }
     pub fn print_log() -> Self {
-        Self { use_sysfunc: true, debug: false, return_print_log: true }
+        Self { use_sysfunc: true, debug: false, return_print_log: true, decimal: false }
     }
 }
 
@@ -483,13 +892,19 @@ pub fn eval(code: &str, options: RunOption) -> Result<NodeValue,String> {
         sys_function_debug::register(&mut context);
     }
     // 字句解析
-    let tokens = tokenizer::tokenize_test(code);
+    let mut tokenizer = tokenizer::Tokenizer::new(code, 0, 0);
+    tokenizer.decimal_mode = options.decimal;
+    let tokens = tokenizer.tokenize();
+    // インデント構文
+    let tokens = indent::convert(code, tokens, "eval")?;
     // 意味解析
-    let mut parser = parser::Parser::new_context(tokens, context.clone());
+    let mut parser = parser::Parser::new_context(tokens, context);
     let nodes = match parser.parse() {
         Ok(nodes) => nodes,
         Err(e) => { return Err(e); }
     };
+    // 構文解析で登録した変数の番号と一致するようにパーサーのコンテキストで実行する
+    let mut context = parser.context;
     // 戻り値として「表示」文のログを返す場合
     if options.return_print_log {
         return match run_nodes(&mut context, &nodes) {
@@ -500,16 +915,25 @@ pub fn eval(code: &str, options: RunOption) -> Result<NodeValue,String> {
     run_nodes(&mut context, &nodes)
 }
 
-/// eval code with context (you can add functions, and set filename)
-pub fn eval_context(ctx: &mut NodeContext, code: &str) -> Result<NodeValue,String> {
+/// コンテキストを複製せずに構文解析する (定義した関数や変数はコンテキストに残る)
+pub(crate) fn parse_context(ctx: &mut NodeContext, code: &str) -> Result<Vec<Node>, String> {
     // 字句解析
     let tokens = tokenizer::tokenize_test(code);
-    // 意味解析
-    let mut parser = parser::Parser::new_context(tokens, ctx.clone());
-    let nodes = match parser.parse() {
-        Ok(nodes) => nodes,
-        Err(e) => { return Err(e); }
-    };
+    // インデント構文
+    let tokens = indent::convert(code, tokens, "eval")?;
+    // 意味解析 (パーサーにコンテキストを一時的に渡して、終わったら戻す)
+    let context = std::mem::replace(ctx, NodeContext::new());
+    let mut parser = parser::Parser::new_context(tokens, context);
+    let result = parser.parse();
+    *ctx = parser.context;
+    result
+}
+
+/// eval code with context (you can add functions, and set filename)
+pub fn eval_context(ctx: &mut NodeContext, code: &str) -> Result<NodeValue,String> {
+    // 前回のエラーは持ち越さない
+    ctx.clear_errors();
+    let nodes = parse_context(ctx, code)?;
     match run_nodes(ctx, &nodes) {
         Ok(_) => Ok(NodeValue::S(String::from(ctx.print_log.trim_end()))),
         Err(e) => Err(e)
@@ -618,6 +1042,10 @@ mod test_runner {
         assert_eq!(res, String::from("2"));
         let res = eval_str("5%2=1と表示");
         assert_eq!(res, String::from("真"));
+        let res = eval_str("「abc」=「abd」と表示");
+        assert_eq!(res, String::from("偽"));
+        let res = eval_str("0.5=0.5と表示");
+        assert_eq!(res, String::from("真"));
     }
     #[test]
     fn test_string_ex() {
@@ -633,6 +1061,13 @@ mod test_runner {
         assert_eq!(res, "6");
     }
 
+    #[test]
+    fn test_eval_userfunc() {
+        // 構文解析で登録した関数を同じ実行で呼び出せる
+        let res = eval_print_str("●倍とは\n2で戻る\nここまで\n倍して表示");
+        assert_eq!(res, "2");
+    }
+
     #[test]
     fn test_calc_long() {
         let res = eval_str("(5から3を引く)を表示。");
@@ -659,6 +1094,704 @@ mod test_runner {
         assert_eq!(res, "1");
     }
 
+    #[test]
+    fn test_array_josi() {
+        // 配列の要素の参照は最後の『]』の助詞を使う
+        let res = eval_str("A=[1,2];もしA[0]が1ならば「真」を表示");
+        assert_eq!(res, "真");
+        let res = eval_str("A=[[1,2],[3,4]];もしA[1][0]が3ならば「真」を表示");
+        assert_eq!(res, "真");
+    }
+
+    #[test]
+    fn test_while() {
+        let res = eval_print_str("N=0;(N<3)の間\nNを表示;N=N+1\nここまで;");
+        assert_eq!(res, "0\n1\n2");
+        let res = eval_str("N=0;N<10の間\nN=N+1;もしN=5ならば抜ける\nここまで;Nを表示");
+        assert_eq!(res, "5");
+        let res = eval_str("N=0;Nが3未満の間、N=N+1。Nを表示");
+        assert_eq!(res, "3");
+        let res = eval_print_str("N=0;永遠の間\nN=N+1;もしN%2=0ならば続ける;もしN>5ならば抜ける;Nを表示\nここまで;");
+        assert_eq!(res, "1\n3\n5");
+    }
+
+    #[test]
+    fn test_for() {
+        let res = eval_print_str("Nを1から3まで繰り返す\nNを表示\nここまで;");
+        assert_eq!(res, "1\n2\n3");
+        // 降順
+        let res = eval_print_str("Nを3から1まで繰り返す\nNを表示\nここまで;");
+        assert_eq!(res, "3\n2\n1");
+        // 増分
+        let res = eval_print_str("Nを0から100まで30ずつ繰り返す\nNを表示\nここまで;");
+        assert_eq!(res, "0\n30\n60\n90");
+        let res = eval_print_str("Nを10から0まで5ずつ繰り返す\nNを表示\nここまで;");
+        assert_eq!(res, "10\n5\n0");
+        let res = eval_print_str("Nを0から1まで0.25ずつ繰り返す\nNを表示\nここまで;");
+        assert_eq!(res, "0\n0.25\n0.5\n0.75\n1");
+        let res = eval_str("S=0;Nを0から1まで0.1ずつ繰り返す、S=S+1。Sを表示");
+        assert_eq!(res, "11");
+        let res = eval_str("Nを1から3まで0ずつ繰り返す\nNを表示\nここまで;");
+        assert!(res.contains("増分"), "{}", res);
+        // 文字列の増分は数値として数える
+        let res = eval_print_str("Nを0から1まで「0.5」ずつ繰り返す\nNを表示\nここまで;");
+        assert_eq!(res, "0\n0.5\n1");
+        let res = eval_print_str("Nを「1」から「3」まで繰り返す\nNを表示\nここまで;");
+        assert_eq!(res, "1\n2\n3");
+        let res = eval_str("Nを1から3まで「abc」ずつ繰り返す\nNを表示\nここまで;");
+        assert!(res.contains("増分"), "{}", res);
+        // isizeに収まらない整数は誤差なく数える
+        let res = eval_print_str("Nを9223372036854775807から9223372036854775807+2まで繰り返す\nNを表示\nここまで;");
+        assert_eq!(res, "9223372036854775807\n9223372036854775808\n9223372036854775809");
+        let res = eval_print_str("Nを0から2^70まで2^70ずつ繰り返す\nNを表示\nここまで;");
+        assert_eq!(res, "0\n1180591620717411303424");
+        // 関数内ではローカル変数になる
+        let res = eval_print_str("●テストとは\nNを1から3まで繰り返す、S=N。Sで戻る\nここまで\nテストを表示;「[{N}]」を表示");
+        assert_eq!(res, "3\n[]");
+    }
+
+    #[test]
+    fn test_foreach() {
+        let res = eval_print_str("[10,20,30]を反復\n「{対象キー}:{対象}」を表示\nここまで;");
+        assert_eq!(res, "0:10\n1:20\n2:30");
+        let res = eval_print_str("A=[1,2,3];Aを反復\nもし対象=2ならば続ける。対象を表示\nここまで;");
+        assert_eq!(res, "1\n3");
+        let res = eval_print_str("[[1,2],[3,4]]を反復\nX=対象キー;対象を反復\n「{X}-{対象キー}」を表示\nここまで\n対象キーを表示\nここまで;");
+        assert_eq!(res, "0-0\n0-1\n0\n1-0\n1-1\n1");
+    }
+
+    #[test]
+    fn test_kai_nested() {
+        let res = eval_print_str("2回\n2回、回数を表示。\n回数を表示\nここまで;");
+        assert_eq!(res, "1\n2\n1\n1\n2\n2");
+        // 繰り返しの後は最後の値が残る
+        let res = eval_print_str("3回、A=1。回数を表示;2回\n3回、A=1。\nここまで;回数を表示");
+        assert_eq!(res, "3\n2");
+        let res = eval_print_str("[1,2]を反復、A=1。「{対象キー}:{対象}」を表示");
+        assert_eq!(res, "1:2");
+    }
+
+    #[test]
+    fn test_switch() {
+        let code = "A=2;Aで条件分岐\n1ならば、「一」と表示\n2ならば\n「二」と表示\nここまで\n違えば\n「他」と表示\nここまで\nここまで;";
+        assert_eq!(eval_print_str(code), "二");
+        let code = "A=5;Aで条件分岐\n1ならば、「一」と表示\n5ならば、「五」と表示\n5ならば、「もう一度五」と表示\nここまで;";
+        assert_eq!(eval_print_str(code), "五");
+        let code = "「赤」で条件分岐\n「青」ならば、「GO」と表示\n違えば、「STOP」と表示\nここまで;";
+        assert_eq!(eval_print_str(code), "STOP");
+        // 数値でない文字列は0と一致しない
+        let code = "「abc」で条件分岐\n0ならば、「零」と表示\n違えば、「他」と表示\nここまで;";
+        assert_eq!(eval_print_str(code), "他");
+        assert_eq!(eval_print_str("もし「abc」=0ならば「真」を表示。違えば「偽」を表示"), "偽");
+        assert_eq!(eval_print_str("もし「12」=12ならば「真」を表示。違えば「偽」を表示"), "真");
+    }
+
+    #[test]
+    fn test_try() {
+        let code = "エラー監視\n「A」と表示\n「問題発生」のエラー発生\n「B」と表示\nエラーならば\n「{エラーメッセージ}@{エラー行}」と表示\nここまで;「C」と表示";
+        assert_eq!(eval_print_str(code), "A\n問題発生@3\nC");
+        let code = "●(Nを)検査\nもしN>2ならば「大きすぎ」のエラー発生\nNで戻る\nここまで\nエラー監視\n1を検査して表示\n5を検査して表示\nエラーならば、エラーメッセージを表示。";
+        assert_eq!(eval_print_str(code), "1\n大きすぎ");
+        let code = "エラー監視\n「OK」と表示\nエラーならば\n「NG」と表示\nここまで;";
+        assert_eq!(eval_print_str(code), "OK");
+        let res = eval_str("「問題」のエラー発生;「続き」と表示");
+        assert!(res.starts_with("!!"));
+    }
+
+    #[test]
+    fn test_dict() {
+        let res = eval_str("A={\"名前\":\"太郎\", \"年齢\": 20};A[\"名前\"]を表示。");
+        assert_eq!(res, "太郎");
+        let res = eval_str("A={名前:「花子」};A[\"年齢\"]=18;A[\"名前\"]=「桜」;Aを表示。");
+        assert_eq!(res, "{\"名前\":\"桜\",\"年齢\":18}");
+        let res = eval_str("A={\"x\":[1,2],\"y\":{\"z\":3}};A[\"y\"][\"z\"]=4;「{A[\"x\"][1]}/{A[\"y\"][\"z\"]}」を表示。");
+        assert_eq!(res, "2/4");
+        let res = eval_print_str("{\"a\":1,\"b\":2}を反復\n「{対象キー}={対象}」を表示\nここまで;");
+        assert_eq!(res, "a=1\nb=2");
+        let res = eval_str("A={};A[\"k\"]を表示。");
+        assert_eq!(res, "");
+    }
+
+    #[test]
+    fn test_const() {
+        let res = eval_str("定数 X=10;X*2を表示");
+        assert_eq!(res, "20");
+        let res = eval_str("定数 X=10\nX=20");
+        assert!(res.contains("定数『X』") && res.contains("(eval:2:1)"), "{}", res);
+        let res = eval_str("PI=3");
+        assert!(res.contains("定数『PI』"), "{}", res);
+        let res = eval_str("3をPIに代入");
+        assert!(res.contains("定数『PI』"), "{}", res);
+        let res = eval_str("定数 A=[1,2];A[0]=5");
+        assert!(res.contains("定数『A』"), "{}", res);
+        let res = eval_str("●テスト\n1で戻る\nここまで\nテスト=2");
+        assert!(res.contains("定数『テスト』"), "{}", res);
+        let res = eval_str("定数 X=1;定数 X=2");
+        assert!(res.contains("定数『X』"), "{}", res);
+        // 繰り返しの変数
+        let res = eval_str("PIを1から2まで繰り返す、PIを表示");
+        assert!(res.contains("定数『PI』"), "{}", res);
+        let res = eval_str("定数 対象=1;[1,2]を反復\n対象を表示\nここまで");
+        assert!(res.contains("定数『対象』"), "{}", res);
+        let res = eval_str("定数 回数=1;2回、回数を表示");
+        assert!(res.contains("定数『回数』"), "{}", res);
+    }
+
+    #[test]
+    fn test_func_local() {
+        // 実行されない分岐の変数があってもローカル変数の番号がずれない
+        let res = eval_str("●(Nで)テスト\nもしN=1ならば\nA=10\n違えば\nB=20\nここまで\nB+1で戻る\nここまで\n0でテストして表示");
+        assert_eq!(res, "21");
+        // 関数から別の関数を呼んでも自分の変数を読む
+        let res = eval_str("●(Aで)二倍\nB=A*2\nBで戻る\nここまで\n●(Aで)計算\nB=A+1\nC=Bで二倍\nB+Cで戻る\nここまで\n3で計算して表示");
+        assert_eq!(res, "12");
+        // 再帰呼び出し
+        let res = eval_str("●(Nの)階乗\nもしN<=1ならば1で戻る\nM=N-1\nX=Mの階乗\nX*Nで戻る\nここまで\n5の階乗を表示");
+        assert_eq!(res, "120");
+        // ローカル変数はグローバルに漏れない
+        let res = eval_print_str("●テスト\nX=5\nXを表示\nここまで\nテスト\n「[{X}]」を表示");
+        assert_eq!(res, "5\n[]");
+        // 定義済みのグローバル変数は関数から書き換えられる
+        let res = eval_str("X=1\n●加算\nX=X+1\nここまで\n加算\n加算\nXを表示");
+        assert_eq!(res, "3");
+        // 『変数』で宣言するとグローバル変数と同名のローカル変数になる
+        let res = eval_print_str("X=1\n●テスト\n変数 X=5\nXを表示\nここまで\nテスト\nXを表示");
+        assert_eq!(res, "5\n1");
+    }
+
+    #[test]
+    fn test_func_value() {
+        // 無名関数を変数に代入して呼ぶ
+        let res = eval_str("F=関数(A,B)\nA+Bで戻る\nここまで\nF(1,2)を表示");
+        assert_eq!(res, "3");
+        // 定義済みの関数を値として渡す
+        let res = eval_str("●(Aを)二倍\nA*2で戻る\nここまで\n●(FとAを)適用\nF(A)で戻る\nここまで\n関数 二倍と5を適用して表示");
+        assert_eq!(res, "10");
+        // 無名関数を引数に渡す
+        let res = eval_str("●(FとAを)適用\nF(A)で戻る\nここまで\nG=関数(X)\nX+1で戻る\nここまで\nGと5を適用して表示");
+        assert_eq!(res, "6");
+        // 関数の中で作った無名関数を返す
+        let res = eval_str("●作成\nY=1\nG=関数(X)\nX*3で戻る\nここまで\nGで戻る\nここまで\nH=作成\nH(2)を表示");
+        assert_eq!(res, "6");
+        // 外側の関数のローカル変数は使えない
+        let res = eval_str("●作成\nZ=1\nG=関数(X)\nX+Zで戻る\nここまで\nGで戻る\nここまで\nH=作成\nH(2)を表示");
+        assert!(res.contains("外側のローカル変数『Z』"), "{}", res);
+        let res = eval_str("●作成\nZ=1\nG=関数(X)\nZ=X*2;Zで戻る\nここまで\nGで戻る\nここまで\nH=作成\nH(2)を表示");
+        assert_eq!(res, "4");
+        // システム関数も値にできる
+        let res = eval_print_str("P=関数 表示\nP(「あいう」)");
+        assert_eq!(res, "あいう");
+        let res = eval_str("F=関数 二倍\n●(Aを)二倍\nA*2で戻る\nここまで\nFの変数型確認して表示");
+        assert_eq!(res, "関数");
+        // 関数ではない値は呼べない
+        let res = eval_str("F=3\nF(1)");
+        assert!(res.contains("呼び出せません"), "{}", res);
+        let res = eval_str("F=関数 存在しない関数");
+        assert!(res.starts_with("!!"), "{}", res);
+    }
+
+    #[test]
+    fn test_josi_args() {
+        // 助詞で引数を割り当てる
+        let res = eval_str("10から3を引いて表示");
+        assert_eq!(res, "7");
+        let res = eval_str("3を10から引いて表示");
+        assert_eq!(res, "7");
+        let res = eval_str("●(AからBを)差\nA-Bで戻る\nここまで\n3を10から差を表示");
+        assert_eq!(res, "7");
+        // 助詞が一致しなければその引数の位置でエラー
+        let res = eval_str("「abc」の「b」が「x」に置換");
+        assert!(res.contains("(eval:1:7)") && res.contains("助詞『が』"), "{}", res);
+        assert!(res.contains("『を』『から』のいずれか"), "{}", res);
+        let res = eval_str("●(AからBを)差\nA-Bで戻る\nここまで\n10から3で差");
+        assert!(res.contains("(eval:4:5)") && res.contains("助詞『で』"), "{}", res);
+    }
+
+    #[test]
+    fn test_default_args() {
+        let code = "●(AとBを{3})足し算\nA+Bで戻る\nここまで\n";
+        let res = eval_str(&format!("{}1と2を足し算して表示", code));
+        assert_eq!(res, "3");
+        let res = eval_str(&format!("{}1と足し算して表示", code));
+        assert_eq!(res, "4");
+        // (B=値を)の書き方
+        let res = eval_str("●(AとB=10を)掛け算\nA*Bで戻る\nここまで\n2と掛け算して表示");
+        assert_eq!(res, "20");
+        // 既定値のない引数は従来どおり「それ」で補う
+        let res = eval_str(&format!("{}10を表示\n5を足し算して表示", code));
+        assert_eq!(res, "15");
+        // 関数の値として呼び出した場合も既定値を使う
+        let res = eval_str(&format!("{}F=関数 足し算\nF(7)を表示", code));
+        assert_eq!(res, "10");
+    }
+
+    #[test]
+    fn test_def_func_towa() {
+        let code = "●(AとBを)合計するとは\nA+Bで戻る\nここまで\n";
+        let res = eval_str(&format!("{}1と2を合計して表示", code));
+        assert_eq!(res, "3");
+        let res = eval_str(&format!("{}3と4を合計した\nそれを表示", code));
+        assert_eq!(res, "7");
+        let res = eval_str(&format!("{}5と6を合計する\nそれを表示", code));
+        assert_eq!(res, "11");
+        // 「とは」を離して書く/引数を後ろに書く
+        let res = eval_str("●(AとBを)合計 とは\nA+Bで戻る\nここまで\n1と2を合計して表示");
+        assert_eq!(res, "3");
+        let res = eval_str("●合計とは(AとBを)\nA+Bで戻る\nここまで\n1と2を合計して表示");
+        assert_eq!(res, "3");
+        // ひらがなの関数名を活用して呼ぶ
+        let code = "●(Aを)かぞえるとは\nA*10で戻る\nここまで\n";
+        let res = eval_str(&format!("{}2をかぞえて表示", code));
+        assert_eq!(res, "20");
+        let res = eval_str(&format!("{}3をかぞえる\nそれを表示", code));
+        assert_eq!(res, "30");
+        let res = eval_str(&format!("{}4をかぞえた\nそれを表示", code));
+        assert_eq!(res, "40");
+        let res = eval_str("●(Aを)たたむとは\nA+1で戻る\nここまで\n1をたたんで表示");
+        assert_eq!(res, "2");
+        // 活用語尾でなければ別の語句
+        let code = "●(Aを)たすとは\nA+1で戻る\nここまで\n";
+        let res = eval_print_str(&format!("{}それ=3;たまを表示", code));
+        assert_eq!(res, "");
+        let res = eval_str(&format!("{}2をたして表示", code));
+        assert_eq!(res, "3");
+        let res = eval_str(&format!("{}3をたまに代入;たまを表示", code));
+        assert_eq!(res, "3");
+        // 活用語尾の後に語句が続く
+        let res = eval_str("●(Aを)みるとは\nA*2で戻る\nここまで\n5をみて表示");
+        assert_eq!(res, "10");
+    }
+
+    #[test]
+    fn test_pass_by_reference() {
+        // 配列の引数は参照渡し
+        let code = "●(Aに Bを)末尾追加するとは\nN=Aの要素数;A[N]=B\nここまで\n";
+        let res = eval_str(&format!("{}X=[1,2];Xに3を末尾追加;Xの要素数を表示", code));
+        assert_eq!(res, "3");
+        let res = eval_str(&format!("{}X=[1,2];Xに3を末尾追加;Xに4を末尾追加;X[3]を表示", code));
+        assert_eq!(res, "4");
+        // 関数のローカル変数を渡す・受け取った引数をさらに渡す
+        let res = eval_str(&format!("{}●(Aを)二つ追加するとは\nAに5を末尾追加;Aに6を末尾追加\nここまで\n●テストとは\n変数のY=[];Yを二つ追加;Z=Y[1];Zで戻る\nここまで\nテストを表示", code));
+        assert_eq!(res, "6");
+        // 辞書
+        let res = eval_str("●(Dを)登録するとは\nD[\"b\"]=2\nここまで\nD={\"a\":1};Dを登録;D[\"b\"]を表示");
+        assert_eq!(res, "2");
+        // 関数の値の呼び出し
+        let res = eval_str(&format!("{}F=関数 末尾追加;X=[];F(X,7);X[0]を表示", code));
+        assert_eq!(res, "7");
+        // 引数そのものへの代入は呼び出し元に影響しない
+        let res = eval_str("●(Aを)置き換えるとは\nA=[9]\nここまで\nX=[1];Xを置き換える;X[0]を表示");
+        assert_eq!(res, "1");
+        // 値は参照渡しにならない
+        let res = eval_str("●(Aを)変更するとは\nA=9\nここまで\nX=1;Xを変更;Xを表示");
+        assert_eq!(res, "1");
+        // 定数は複製して渡すので書き換わらない
+        let code = "●(Aを)壊すとは\nA[0]=9\nここまで\n";
+        let res = eval_str(&format!("{}定数 X=[1];Xを壊す;X[0]を表示", code));
+        assert_eq!(res, "1");
+        let res = eval_str(&format!("{}●試すとは\n定数 Y=[1];Yを壊す;Y[0]で戻る\nここまで\n試して表示", code));
+        assert_eq!(res, "1");
+        // 参照渡しされた引数を返しても、戻り値を変更して元の値は変わらない
+        let code = "●(Aを)返却するとは\nAで戻る\nここまで\n";
+        let res = eval_print_str(&format!("{}X=[1];Y=Xを返却;Y[0]=9;X[0]を表示;Xを返却;それ[0]=8;X[0]を表示", code));
+        assert_eq!(res, "1\n1");
+        let res = eval_str(&format!("{}●(Aを)試すとは\nAを返却;それ[\"a\"]=9\nここまで\nX={{\"a\":1}};Xを試す;X[\"a\"]を表示", code));
+        assert_eq!(res, "1");
+    }
+
+    #[test]
+    fn test_record_type() {
+        let code = "●人とは型 (名前, 年齢=0)\n";
+        let res = eval_str(&format!("{}P=人(「太郎」, 20);P[\"名前\"]を表示", code));
+        assert_eq!(res, "太郎");
+        let res = eval_str(&format!("{}P=人(「太郎」, 20);Pの年齢を表示", code));
+        assert_eq!(res, "20");
+        let res = eval_str(&format!("{}P=人(「太郎」);Pの年齢+1を表示", code));
+        assert_eq!(res, "1");
+        let res = eval_str(&format!("{}P=人(「太郎」, 20);P[\"年齢\"]=21;Pを表示", code));
+        assert_eq!(res, "人{\"名前\":\"太郎\",\"年齢\":21}");
+        let res = eval_str(&format!("{}P=人(「太郎」, 20);PのTYPEOFを表示", code));
+        assert_eq!(res, "人");
+        // 定義されていないフィールドには代入できない
+        let res = eval_str(&format!("{}P=人(「太郎」);P[\"住所\"]=「東京」", code));
+        assert!(res.contains("代入に失敗"), "{}", res);
+        let res = eval_str(&format!("{}P=人(「太郎」, 20, 30)", code));
+        assert!(res.contains("多すぎます"), "{}", res);
+        // メソッド
+        let code = "●人とは型 (名前, 年齢)\n●人の挨拶とは\n「私は{自身の名前}です」で戻る\nここまで\n●(Nを)人の加齢するとは\n自身[\"年齢\"]=自身の年齢+N\nここまで\n";
+        let res = eval_str(&format!("{}P=人(「花子」, 10);Pの挨拶を表示", code));
+        assert_eq!(res, "私は花子です");
+        let res = eval_str(&format!("{}P=人(「花子」, 10);Pの3を加齢;Pの年齢を表示", code));
+        assert_eq!(res, "13");
+        // 型ごとに同じ名前のメソッドを呼び分ける
+        let code2 = "●犬とは型 (名前)\n●犬の挨拶とは\n「ワン」で戻る\nここまで\n";
+        let res = eval_print_str(&format!("{}{}A=[人(「花子」, 10), 犬(「ポチ」)];Aを反復\n対象の挨拶を表示\nここまで", code, code2));
+        assert_eq!(res, "私は花子です\nワン");
+        let res = eval_str(&format!("{}3の挨拶を表示", code));
+        assert!(res.contains("メソッド『挨拶』がありません"), "{}", res);
+        // 型の値でなければ同じ名前の関数を呼ぶ
+        let code3 = "●(Xの)挨拶とは\n「{X}さん、こんにちは」で戻る\nここまで\n";
+        let res = eval_print_str(&format!("{}{}「太郎」の挨拶を表示;人(「花子」)の挨拶を表示", code, code3));
+        assert_eq!(res, "太郎さん、こんにちは\n私は花子です");
+        // 引数の形が違うメソッドはエラー
+        let code4 = "●猫とは型 (名前)\n●(Nで)猫の挨拶とは\n「ニャー」で戻る\nここまで\n";
+        let res = eval_str(&format!("{}{}猫(「タマ」)の3で挨拶", code, code4));
+        assert!(res.contains("引数の形"), "{}", res);
+        // フィールドと同じ名前の関数は型の値以外で呼ばれる
+        let code5 = "●商品とは型 (要素数, 名前)\n";
+        let res = eval_print_str(&format!("{}A=[1,2,3];Aの要素数を表示;「abc」の要素数を表示;商品(5)の要素数を表示", code5));
+        assert_eq!(res, "3\n3\n5");
+        let res = eval_str(&format!("{}P=商品(5);Pの要素数を2増やす;Pの要素数を表示", code5));
+        assert_eq!(res, "7");
+    }
+
+    #[test]
+    fn test_bigint() {
+        // 桁あふれしたら任意精度の整数になる
+        let res = eval_str("9223372036854775807+1を表示");
+        assert_eq!(res, "9223372036854775808");
+        let res = eval_str("(0-9223372036854775807)-10を表示");
+        assert_eq!(res, "-9223372036854775817");
+        let res = eval_str("2^100を表示");
+        assert_eq!(res, "1267650600228229401496703205376");
+        let res = eval_str("2の100のべき乗を表示");
+        assert_eq!(res, "1267650600228229401496703205376");
+        // 指数が大きくても切り捨てない
+        let res = eval_str("0^(2^33)を表示");
+        assert_eq!(res, "0");
+        let res = eval_str("(0-1)^(2^33+1)を表示");
+        assert_eq!(res, "-1");
+        let res = eval_str("2^(0-2^33)を表示");
+        assert_eq!(res, "0");
+        let res = eval_str("N=1;Iを1から30まで繰り返す、N=N*I。Nを表示");
+        assert_eq!(res, "265252859812191058636308480000000");
+        // 計算結果が小さくなればisizeに戻る
+        let res = eval_str("A=2^70;B=A/2^69;(A-A+5)の変数型確認を表示");
+        assert_eq!(res, "I");
+        let res = eval_str("(2^70)%(2^69+1)を表示");
+        assert_eq!(res, "590295810358705651711");
+        let res = eval_str("123456789012345678901234567890を表示");
+        assert_eq!(res, "123456789012345678901234567890");
+        // 文字列と整数の足し算
+        let res = eval_str("「99999999999999999999」+1を表示");
+        assert_eq!(res, "100000000000000000000");
+        let res = eval_str("「abc」+1を表示");
+        assert!(res.contains("数値に変換できない"), "{}", res);
+        // 割り切れる整数の割り算は誤差なく計算する
+        let res = eval_str("((0-9223372036854775807)-1)/(0-1)を表示");
+        assert_eq!(res, "9223372036854775808");
+        let res = eval_str("(2^70+2)/2を表示");
+        assert_eq!(res, "590295810358705651713");
+        // 比較
+        let res = eval_str("2^64>2^63と表示");
+        assert_eq!(res, "真");
+        let res = eval_str("2^64=18446744073709551616と表示");
+        assert_eq!(res, "真");
+        let res = eval_str("(0-2^64)<3と表示");
+        assert_eq!(res, "真");
+        // 0で割った余り
+        let res = eval_str("5%0を表示");
+        assert_eq!(res, "NaN");
+    }
+
+    #[test]
+    fn test_indent_syntax() {
+        let res = eval_print_str("!インデント構文\nA=5\nもしA>3ならば\n  「大」と表示\n違えば\n  「小」と表示\n「終」と表示");
+        assert_eq!(res, "大\n終");
+        let res = eval_print_str("!インデント構文\nIを1から2まで繰り返す\n  Jを1から2まで繰り返す\n    (I*J)を表示\n「終」と表示");
+        assert_eq!(res, "1\n2\n2\n4\n終");
+        let res = eval_str("!インデント構文\n●(AとBを)加算とは\n　　A+Bで戻る\n2と3を加算して表示");
+        assert_eq!(res, "5");
+        let res = eval_print_str("!インデント構文\n2で条件分岐\n  1ならば\n    「一」と表示\n  2ならば\n    「二」と表示\n  違えば\n    「他」と表示\n「終」と表示");
+        assert_eq!(res, "二\n終");
+        let res = eval_print_str("!インデント構文\nエラー監視\n  「失敗」のエラー発生\nエラーならば\n  「捕捉」と表示\n「終」と表示");
+        assert_eq!(res, "捕捉\n終");
+        let res = eval_str("!インデント構文\nもし1ならば\n    「A」と表示\n  「B」と表示");
+        assert!(res.contains("インデントが揃っていません"), "{}", res);
+    }
+
+    #[test]
+    fn test_dncl() {
+        // 配列の合計 (配列の添字は1から)
+        let res = eval_print_str(concat!(
+            "!DNCLモード\n",
+            "Tokuten ← {87, 45, 72, 100}\n",
+            "goukei ← 0\n",
+            "i を 1 から 要素数(Tokuten) まで 1 ずつ増やしながら，\n",
+            "｜ goukei ← goukei + Tokuten[i]\n",
+            "を繰り返す\n",
+            "「合計は」と goukei と「点」を表示する\n",
+            "Tokuten[1] を表示する"));
+        assert_eq!(res, "合計は304点\n87");
+        // FizzBuzz
+        let res = eval_print_str(concat!(
+            "!DNCLモード\n",
+            "x を 9 から 15 まで 1 ずつ増やしながら，\n",
+            "｜ もし x ％ 15 = 0 ならば\n",
+            "｜ ｜ 「FizzBuzz」を表示する\n",
+            "｜ を実行し，そうでなくもし x ％ 3 = 0 ならば\n",
+            "｜ ｜ 「Fizz」を表示する\n",
+            "｜ を実行し，そうでなくもし x ％ 5 = 0 ならば\n",
+            "｜ ｜ 「Buzz」を表示する\n",
+            "｜ を実行し，そうでなければ\n",
+            "｜ ｜ x を表示する\n",
+            "｜ を実行する\n",
+            "を繰り返す"));
+        assert_eq!(res, "Fizz\nBuzz\n11\nFizz\n13\n14\nFizzBuzz");
+        // 各桁の和 (前判定の繰り返し)
+        let res = eval_print_str(concat!(
+            "!DNCLモード\n",
+            "x ← 1234，wa ← 0\n",
+            "x ＞ 0 の間，\n",
+            "｜ wa ← wa + x ％ 10\n",
+            "｜ x ← 整数(x ÷ 10)\n",
+            "を繰り返す\n",
+            "wa を表示する"));
+        assert_eq!(res, "10");
+        // ÷ は整数の商
+        let res = eval_print_str(concat!(
+            "!DNCLモード\n",
+            "x ← 7 ÷ 2\n",
+            "x を表示する\n",
+            "Data ← {10, 20, 30}\n",
+            "i ← 1 + 要素数(Data) ÷ 2\n",
+            "Data[i] と「,」と 17 ÷ 5 × 2 を表示する"));
+        assert_eq!(res, "3\n20,6");
+        // バブルソート (減らしながら)
+        let res = eval_print_str(concat!(
+            "!DNCLモード\n",
+            "Data ← {5, 3, 8, 1}\n",
+            "i を 要素数(Data) - 1 から 1 まで 1 ずつ減らしながら，\n",
+            "｜ j を 1 から i まで 1 ずつ増やしながら，\n",
+            "｜ ｜ もし Data[j] ＞ Data[j + 1] ならば\n",
+            "｜ ｜ ｜ tmp ← Data[j]，Data[j] ← Data[j + 1]，Data[j + 1] ← tmp\n",
+            "｜ ｜ を実行する\n",
+            "｜ を繰り返す\n",
+            "を繰り返す\n",
+            "Data[1] と「,」と Data[2] と「,」と Data[3] と「,」と Data[4] を表示する"));
+        assert_eq!(res, "1,3,5,8");
+        // 後判定の繰り返し
+        let res = eval_print_str(concat!(
+            "!DNCLモード\n",
+            "x ← 1\n",
+            "を繰り返し，\n",
+            "｜ x ← x × 2\n",
+            "を，x ≧ 100 になるまで実行する\n",
+            "x を表示する"));
+        assert_eq!(res, "128");
+        // 外部からの入力
+        let mut ctx = NodeContext::new();
+        sys_function::register(&mut ctx);
+        ctx.input_fn = Some(|_| String::from("21"));
+        let res = eval_context(&mut ctx, "!DNCLモード\nn ← 【外部からの入力】\nn × 2 を表示する").unwrap();
+        assert_eq!(res.to_string(), "42");
+        // 文字列の中の『!DNCLモード』では切り替えない
+        let res = eval_print_str("S=「\n!DNCLモード\n」;A=[5,6];A[1]を表示");
+        assert_eq!(res, "6");
+    }
+
+    #[test]
+    fn test_eval_context() {
+        // 前回定義した関数を使える
+        let mut ctx = NodeContext::new();
+        sys_function::register(&mut ctx);
+        eval_context(&mut ctx, "●(Aを)倍増とは\nA*2で戻る\nここまで").unwrap();
+        let res = eval_context(&mut ctx, "3を倍増して表示").unwrap();
+        assert_eq!(res.to_string(), "6");
+    }
+
+    #[test]
+    fn test_sysfunc_closure() {
+        // 値を捕捉したクロージャを登録できる
+        let mut ctx = NodeContext::new();
+        sys_function::register(&mut ctx);
+        let counter = Rc::new(RefCell::new(0));
+        let c = Rc::clone(&counter);
+        ctx.add_sysfunc("数", sysargs(&[&["を"]]), move |_, args| {
+            *c.borrow_mut() += args[0].to_int(0);
+            Some(NodeValue::I(*c.borrow()))
+        });
+        let res = eval_context(&mut ctx, "3を数える;4を数えて表示").unwrap();
+        assert_eq!(res.to_string(), "7");
+        assert_eq!(*counter.borrow(), 7);
+        // ホストアプリのデータを借用できる
+        struct Store { items: Vec<String> }
+        ctx.set_user_data(Store { items: vec![] });
+        ctx.add_sysfunc("保存", sysargs(&[&["を"]]), |ctx, args| {
+            let mut store = ctx.user_data_mut::<Store>()?;
+            store.items.push(args[0].to_string());
+            Some(NodeValue::I(store.items.len() as isize))
+        });
+        ctx.print_log.clear();
+        let res = eval_context(&mut ctx, "「あ」を保存;「い」を保存して表示").unwrap();
+        assert_eq!(res.to_string(), "2");
+        assert_eq!(ctx.user_data::<Store>().unwrap().items, vec!["あ", "い"]);
+        assert!(ctx.user_data::<String>().is_none());
+        assert!(ctx.remove_user_data::<Store>());
+        assert!(ctx.user_data::<Store>().is_none());
+    }
+
+    #[test]
+    fn test_add_func() {
+        // Rustの関数を引数の型を確かめて呼び出す
+        let mut ctx = NodeContext::new();
+        sys_function::register(&mut ctx);
+        ctx.add_func("加算", &[&["と"], &["を"]], |a: i64, b: i64| a + b);
+        ctx.add_func("連結", &[&["を"], &["で"]], |list: Vec<String>, sep: String| list.join(&sep));
+        ctx.add_func("挨拶", &[&["に"]], |name: Option<String>| format!("こんにちは{}", name.unwrap_or_default()));
+        let res = eval_context(&mut ctx, "1と2を加算して表示").unwrap();
+        assert_eq!(res.to_string(), "3");
+        ctx.print_log.clear();
+        let res = eval_context(&mut ctx, "[「a」,「b」]を「-」で連結して表示").unwrap();
+        assert_eq!(res.to_string(), "a-b");
+        ctx.print_log.clear();
+        let res = eval_context(&mut ctx, "「花子」に挨拶して表示").unwrap();
+        assert_eq!(res.to_string(), "こんにちは花子");
+        // 型が合わなければ実行時エラー
+        let res = eval_context(&mut ctx, "1と「あ」を加算して表示").unwrap_err();
+        assert!(res.contains("『加算』の2番目の引数には整数が必要ですが『あ』が指定されました。"), "{}", res);
+        let res = eval_context(&mut ctx, "「x」を「-」で連結して表示").unwrap_err();
+        assert!(res.contains("文字列の配列が必要"), "{}", res);
+        // エラー監視で捕まえられる
+        ctx.print_log.clear();
+        let res = eval_context(&mut ctx, "エラー監視\n1と「あ」を加算\nエラーならば\n「捕捉」と表示\nここまで").unwrap();
+        assert_eq!(res.to_string(), "捕捉");
+    }
+
+    #[test]
+    fn test_add_func_josi_len() {
+        // 助詞と引数の数が違えば登録しない
+        let mut ctx = NodeContext::new();
+        let res = ctx.try_add_func("加算", &[&["と"]], |a: i64, b: i64| a + b);
+        assert_eq!(res, Err(String::from("『加算』の助詞と引数の数が違います。")));
+        assert!(ctx.find_var_info("加算").is_none());
+        ctx.add_func("減算", &[&["から"]], |a: i64, b: i64| a - b);
+        assert!(ctx.has_error());
+        assert!(ctx.find_var_info("減算").is_none());
+    }
+
+    #[test]
+    fn test_host_api() {
+        let mut ctx = NodeContext::new();
+        ctx.set_filename("host");
+        sys_function::register(&mut ctx);
+        // 実行前に設定した変数をプログラムから使い、結果を読み出す
+        ctx.set_global("入力", NodeValue::I(5)).unwrap();
+        eval_context(&mut ctx, concat!(
+            "結果=入力*2\n",
+            "●(AとBを)合計とは\nA+B+入力で戻る\nここまで\n",
+            "●(Aを)失敗とは\nAのエラー発生\nここまで\n",
+        )).unwrap();
+        assert_eq!(ctx.get_global("結果").unwrap().to_string(), "10");
+        assert!(ctx.get_global("未定義").is_err());
+        // 関数を名前で呼び出す
+        ctx.set_global("入力", NodeValue::I(100)).unwrap();
+        let res = ctx.call_func("合計", vec![NodeValue::I(1), NodeValue::I(2)]).unwrap();
+        assert_eq!(res.to_string(), "103");
+        let res = ctx.call_func("足", vec![NodeValue::I(1), NodeValue::I(2)]).unwrap();
+        assert_eq!(res.to_string(), "3");
+        // エラーはNodeErrorで返し、コンテキストには残さない
+        let err = ctx.call_func("失敗", vec![NodeValue::from_str("問題")]).unwrap_err();
+        assert!(err.message.contains("問題"), "{}", err.to_string());
+        assert!(!ctx.has_error());
+        assert!(ctx.call_func("無い関数", vec![]).is_err());
+        assert!(ctx.call_func("合計", vec![NodeValue::I(1); 3]).is_err());
+        assert!(ctx.set_global("合計", NodeValue::I(1)).is_err());
+        let res = ctx.call_func("合計", vec![NodeValue::I(1), NodeValue::I(2)]).unwrap();
+        assert_eq!(res.to_string(), "103");
+    }
+
+    #[test]
+    fn test_number_literal() {
+        let res = eval_str("0xFF+0b11+0o10を表示");
+        assert_eq!(res, "266");
+        let res = eval_str("1_000*1e3を表示");
+        assert_eq!(res, "1000000");
+        let res = eval_str("百二十三+3万を表示");
+        assert_eq!(res, "30123");
+        let res = eval_str("N=0;三回、N=N+1。Nを表示");
+        assert_eq!(res, "3");
+        let res = eval_str("4の二乗を表示");
+        assert_eq!(res, "16");
+        // 書き方の誤った数値はエラー
+        let res = eval_str("0x_を表示");
+        assert!(res.contains("数値『0x_』"), "{}", res);
+        let res = eval_str("3百百を表示");
+        assert!(res.contains("数値『3百百』"), "{}", res);
+    }
+
+    #[test]
+    fn test_decimal() {
+        // 小数モードでは小数を誤差なく計算する
+        let res = eval_str("0.1+0.2=0.3と表示");
+        assert_eq!(res, "偽");
+        let res = eval_str("!小数モード\n0.1+0.2=0.3と表示");
+        assert_eq!(res, "真");
+        let res = eval_str("!小数モード\n0.1+0.2を表示");
+        assert_eq!(res, "0.3");
+        let res = eval_str("!小数モード\n1.10*3を表示");
+        assert_eq!(res, "3.3");
+        let res = eval_str("!小数モード\n1.00-0.01*3を表示");
+        assert_eq!(res, "0.97");
+        let res = eval_str("!小数モード\n10/4を表示");
+        assert_eq!(res, "2.5");
+        let res = eval_str("!小数モード\n1.0/3を表示");
+        assert_eq!(res, "0.33333333333333333333");
+        let res = eval_str("!小数モード\n0.1の変数型確認を表示");
+        assert_eq!(res, "D");
+        let res = eval_str("!小数モード\nN=0;Iを0から1まで0.1ずつ繰り返す、N=N+1。Nを表示");
+        assert_eq!(res, "11");
+        let res = eval_str("!小数モード\n1.05>1.0499と表示");
+        assert_eq!(res, "真");
+        // べき乗は小数で計算する
+        let res = eval_str("!小数モード\n4^0.5を表示");
+        assert_eq!(res, "2");
+        // 桁あふれしたらエラー
+        let res = eval_str("!小数モード\n2000000000000000000/0.5を表示");
+        assert!(res.contains("桁あふれ"), "{}", res);
+        let res = eval_str("!小数モード\n0.1234567890123456789012345678901234567890+1を表示");
+        assert!(res.contains("桁数が多すぎます"), "{}", res);
+        let res = eval_str("「1e300」の小数を表示");
+        assert!(res.contains("変換できません"), "{}", res);
+        // 実行オプションで指定する
+        let opt = RunOption { decimal: true, ..RunOption::print_log() };
+        let res = eval("0.7+0.1を表示", opt).unwrap();
+        assert_eq!(res.to_string(), "0.8");
+    }
+
+    #[test]
+    fn test_inc_dec() {
+        let res = eval_str("N=1;Nを2増やす;Nを表示");
+        assert_eq!(res, "3");
+        let res = eval_str("N=10;Nを3減らす;Nを表示");
+        assert_eq!(res, "7");
+        let res = eval_str("N=10;Nを増やす;Nを表示");
+        assert_eq!(res, "11");
+        let res = eval_str("A=[1,2,3];A[1]を5増やす;A[1]を表示");
+        assert_eq!(res, "7");
+        let res = eval_str("●テストとは\n変数のN=1;Nを2増やす;Nで戻る\nここまで\nテストを表示");
+        assert_eq!(res, "3");
+        let res = eval_str("定数 N=1;Nを2増やす");
+        assert!(res.contains("定数『N』"), "{}", res);
+        let res = eval_str("定数 N=1;N+=2");
+        assert!(res.contains("定数『N』"), "{}", res);
+        // 複合代入
+        let res = eval_str("N=1;N+=5;Nを表示");
+        assert_eq!(res, "6");
+        let res = eval_str("N=1;N-=5;Nを表示");
+        assert_eq!(res, "-4");
+        let res = eval_str("N=3;N*=5;Nを表示");
+        assert_eq!(res, "15");
+        let res = eval_str("N=10;N/=4;Nを表示");
+        assert_eq!(res, "2.5");
+        let res = eval_str("S=「あ」;S+=「い」;Sを表示");
+        assert_eq!(res, "あい");
+        let res = eval_str("A=[1,2];A[1]+=10;A[1]を表示");
+        assert_eq!(res, "12");
+        let res = eval_str("A=[[1,2],[3,4]];A[1][0]*=10;A[1][0]を表示");
+        assert_eq!(res, "30");
+    }
+
     #[test]
     fn test_renbun() {
         let res = eval_str("1に2を足して3を足して表示。");
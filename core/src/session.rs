@@ -0,0 +1,131 @@
+//! セッション ... 関数や変数の定義を保持したまま、続けてプログラムを実行する
+
+use crate::node::*;
+use crate::runner;
+use crate::sys_function;
+
+/// 一つのコンテキストを共有して、プログラムを何度も実行するためのセッション
+pub struct Session {
+    pub context: NodeContext,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    /// 標準関数を登録したセッションを作る
+    pub fn new() -> Self {
+        let mut context = NodeContext::new();
+        context.set_filename("session");
+        sys_function::register(&mut context);
+        Self { context }
+    }
+    /// 関数などを登録済みのコンテキストからセッションを作る
+    pub fn from_context(context: NodeContext) -> Self {
+        Self { context }
+    }
+    /// プログラムを実行して最後の文の値を返す (前回までに定義した関数や変数を使える)
+    pub fn eval(&mut self, code: &str) -> Result<NodeValue, String> {
+        let nodes = self.parse(code)?;
+        self.run(&nodes)
+    }
+    /// プログラムを構文解析する (定義した関数はこの時点でセッションに登録される)
+    pub fn parse(&mut self, code: &str) -> Result<Vec<Node>, String> {
+        self.reset_state();
+        runner::parse_context(&mut self.context, code)
+    }
+    /// 構文解析したプログラムを実行して最後の文の値を返す
+    pub fn run(&mut self, nodes: &[Node]) -> Result<NodeValue, String> {
+        self.context.clear_errors();
+        runner::run_nodes(&mut self.context, nodes)
+    }
+    /// グローバル変数の名前と値の一覧 (関数と型、「それ」は除く)
+    pub fn global_vars(&self) -> Vec<(String, NodeValue)> {
+        let scope = &self.context.scopes.scopes[1];
+        scope.get_names().into_iter().enumerate()
+            .filter(|(no, name)| name != "それ" && matches!(scope.var_metas[*no].kind,
+                NodeVarKind::Empty | NodeVarKind::Bool | NodeVarKind::Number |
+                NodeVarKind::String | NodeVarKind::Array | NodeVarKind::Dict))
+            .map(|(no, name)| (name, scope.var_values[no].clone()))
+            .collect()
+    }
+    /// ユーザー関数の名前と引数の一覧
+    pub fn user_funcs(&self) -> Vec<(String, Vec<SysArg>)> {
+        let scope = &self.context.scopes.scopes[1];
+        scope.get_names().into_iter().enumerate()
+            .filter_map(|(no, name)| match &scope.var_metas[no].kind {
+                NodeVarKind::UserFunc(args) => Some((name, args.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+    /// 「それ」の値を空にする
+    pub fn reset_sore(&mut self) {
+        self.context.scopes.set_value(1, "それ", NodeValue::Empty);
+    }
+    /// 「表示」文のログを消去する
+    pub fn clear_print_log(&mut self) {
+        self.context.print_log.clear();
+    }
+    /// 「表示」文のログを得る
+    pub fn get_print_log(&self) -> &str {
+        &self.context.print_log
+    }
+    /// 前回の実行で残ったエラーや「抜ける」「戻る」の状態、関数のスコープを片付ける
+    fn reset_state(&mut self) {
+        let ctx = &mut self.context;
+        ctx.clear_errors();
+        ctx.try_break = None;
+        ctx.try_continue = None;
+        ctx.try_return = None;
+        ctx.callstack_level = 0;
+        while ctx.scopes.pop_local().is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod test_session {
+    use super::*;
+
+    #[test]
+    fn test_session() {
+        let mut s = Session::new();
+        // 関数と変数の定義を次の実行で使える
+        s.eval("●(Aを)倍増とは\nA*2で戻る\nここまで\nN=5").unwrap();
+        assert_eq!(s.eval("Nを倍増").unwrap().to_string(), "10");
+        s.eval("N=N+1").unwrap();
+        assert_eq!(s.eval("Nを倍増").unwrap().to_string(), "12");
+        // 表示のログ
+        s.eval("「あ」を表示").unwrap();
+        s.eval("「い」を表示").unwrap();
+        assert_eq!(s.get_print_log(), "あ\nい\n");
+        s.clear_print_log();
+        s.eval("「う」を表示").unwrap();
+        assert_eq!(s.get_print_log(), "う\n");
+        // それ
+        s.eval("3に4を足す").unwrap();
+        assert_eq!(s.eval("それに1を足す").unwrap().to_string(), "8");
+        s.reset_sore();
+        assert_eq!(s.context.scopes.scopes[1].get_var("それ"), NodeValue::Empty);
+        // エラーがあっても次の実行に影響しない
+        assert!(s.eval("「失敗」のエラー発生").is_err());
+        assert!(s.eval("●壊れたとは\n(1+").is_err());
+        assert_eq!(s.eval("Nを倍増").unwrap().to_string(), "12");
+    }
+    #[test]
+    fn test_list() {
+        let mut s = Session::new();
+        s.eval("A=1\nB=「あ」\n●(XとYを)加算とは\nX+Yで戻る\nここまで").unwrap();
+        let vars: Vec<String> = s.global_vars().iter().map(|(n, v)| format!("{}={}", n, v.to_string())).collect();
+        assert_eq!(vars, vec!["A=1", "B=あ"]);
+        let funcs: Vec<String> = s.user_funcs().iter().map(|(n, args)| format!("{}:{}", n, args.len())).collect();
+        assert_eq!(funcs, vec!["加算:2"]);
+        // 文ごとに実行する
+        let nodes = s.parse("1と2を加算\n3と4を加算").unwrap();
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(s.run(&nodes[1..]).unwrap().to_string(), "7");
+    }
+}
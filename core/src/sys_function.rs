@@ -1,33 +1,45 @@
 //! なでしこの標準関数を定義したもの
 
 use crate::node::*;
+use crate::bigint::BigInt;
+use crate::decimal::Decimal;
 
 /// 関数をシステムに登録する
 pub fn register(ctx: &mut NodeContext) {
     // 表示
     ctx.add_sysfunc("表示", sysargs(&[&["を", "と"]]), sys_print);
     // 四則演算
-    ctx.add_sysfunc("足", sysargs(&[&["と","に"], &["を"]]), sys_add);
-    ctx.add_sysfunc("引", sysargs(&[&["から"], &["を"]]), sys_sub);
-    ctx.add_sysfunc("掛", sysargs(&[&["と","に"], &["を"]]), sys_mul);
-    ctx.add_sysfunc("割", sysargs(&[&["を"], &["で"]]), sys_div);
-    ctx.add_sysfunc("割余", sysargs(&[&["を"], &["で"]]), sys_mod);
-    ctx.add_sysfunc("倍", sysargs(&[&["の", "を"], &[""]]), sys_mul);
-    ctx.add_sysfunc("二乗", sysargs(&[&["の", "を"]]), sys_pow2);
-    ctx.add_sysfunc("べき乗", sysargs(&[&["の"], &["の"]]), sys_pow);
-    ctx.add_sysfunc("以上", sysargs(&[&["が"], &[""]]), sys_gteq);
-    ctx.add_sysfunc("以下", sysargs(&[&["が"], &[""]]), sys_lteq);
-    ctx.add_sysfunc("超", sysargs(&[&["が"], &[""]]), sys_gt);
-    ctx.add_sysfunc("未満", sysargs(&[&["が"], &[""]]), sys_lt);
-    ctx.add_sysfunc("等", sysargs(&[&["が"], &["と"]]), sys_eq);
-    ctx.add_sysfunc("範囲内", sysargs(&[&["が"], &["から"], &["の", "までの"]]), sys_eq);
+    ctx.add_func("足", &[&["と","に"], &["を"]], sys_add);
+    ctx.add_func("引", &[&["から"], &["を"]], sys_sub);
+    ctx.add_func("掛", &[&["と","に"], &["を"]], sys_mul);
+    ctx.add_func("割", &[&["を"], &["で"]], sys_div);
+    ctx.add_func("割余", &[&["を"], &["で"]], sys_mod);
+    ctx.add_func("倍", &[&["の", "を"], &[""]], sys_mul);
+    ctx.add_func("二乗", &[&["の", "を"]], sys_pow2);
+    ctx.add_func("べき乗", &[&["の"], &["の"]], sys_pow);
+    ctx.add_func("以上", &[&["が"], &[""]], sys_gteq);
+    ctx.add_func("以下", &[&["が"], &[""]], sys_lteq);
+    ctx.add_func("超", &[&["が"], &[""]], sys_gt);
+    ctx.add_func("未満", &[&["が"], &[""]], sys_lt);
+    ctx.add_func("等", &[&["が"], &["と"]], sys_eq);
+    ctx.add_sysfunc("範囲内", sysargs(&[&["が"], &["から"], &["の", "までの"]]), |_, args| Some(sys_eq(args[0].clone(), args[1].clone())));
     // 型変換
-    ctx.add_sysfunc("TYPEOF", sysargs(&[&["の"]]), sys_typeof);
-    ctx.add_sysfunc("変数型確認", sysargs(&[&["の"]]), sys_typeof);
-    ctx.add_sysfunc("INT", sysargs(&[&["の"]]), sys_toint);
-    ctx.add_sysfunc("FLOAT", sysargs(&[&["の"]]), sys_tofloat);
-    ctx.add_sysfunc("HEX", sysargs(&[&["の"]]), sys_hex);
-    ctx.add_sysfunc("二進", sysargs(&[&["の"]]), sys_bin);
+    ctx.add_func("TYPEOF", &[&["の"]], sys_typeof);
+    ctx.add_func("変数型確認", &[&["の"]], sys_typeof);
+    ctx.add_func("INT", &[&["の"]], sys_toint);
+    ctx.add_func("FLOAT", &[&["の"]], sys_tofloat);
+    ctx.add_sysfunc("小数", sysargs(&[&["の"]]), sys_todecimal);
+    ctx.add_sysfunc("DECIMAL", sysargs(&[&["の"]]), sys_todecimal);
+    ctx.add_func("HEX", &[&["の"]], sys_hex);
+    ctx.add_func("二進", &[&["の"]], sys_bin);
+    // 入力
+    ctx.add_sysfunc("尋", sysargs(&[&["と", "を"]]), sys_input);
+    // 配列
+    ctx.add_func("要素数", &[&["の"]], sys_len);
+    // 文字列
+    ctx.add_func("置換", &[&["の", "で"], &["を", "から"], &["に", "へ"]], sys_replace);
+    // エラー
+    ctx.add_sysfunc("エラー発生", sysargs(&[&["の", "で"]]), sys_throw_error);
     // 定数
     ctx.add_sysconst("永遠", NodeValue::B(true));
     ctx.add_sysconst("オン", NodeValue::B(true));
@@ -53,109 +65,132 @@ pub fn sys_print(ctx: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValu
     Some(NodeValue::S(s))
 } 
 
-fn sys_add(_: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
-    let a = &args[0];
-    let b = &args[1];
-    let res = NodeValue::calc_plus(&a, &b);
-    Some(res)
+fn sys_add(a: NodeValue, b: NodeValue) -> NodeValue {
+    NodeValue::calc_plus(&a, &b)
 }
-fn sys_sub(_: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
-    let a = &args[0];
-    let b = &args[1];
-    let res = NodeValue::calc_minus(&a, &b);
-    Some(res)
+fn sys_sub(a: NodeValue, b: NodeValue) -> NodeValue {
+    NodeValue::calc_minus(&a, &b)
 }
-fn sys_mul(_: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
-    let a = &args[0];
-    let b = &args[1];
-    let res = NodeValue::calc_mul(&a, &b);
-    Some(res)
+fn sys_mul(a: NodeValue, b: NodeValue) -> NodeValue {
+    NodeValue::calc_mul(&a, &b)
 }
-fn sys_div(_: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
-    let a = &args[0];
-    let b = &args[1];
-    let res = NodeValue::calc_div(&a, &b);
-    Some(res)
+fn sys_div(a: NodeValue, b: NodeValue) -> NodeValue {
+    NodeValue::calc_div(&a, &b)
 }
-fn sys_mod(_: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
-    let a = &args[0];
-    let b = &args[1];
-    let res = NodeValue::calc_mod(&a, &b);
-    Some(res)
+fn sys_mod(a: NodeValue, b: NodeValue) -> NodeValue {
+    NodeValue::calc_mod(&a, &b)
 }
-fn sys_pow(_: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
-    let a = &args[0];
-    let b = &args[1];
-    match (a, b) {
-        (NodeValue::I(av), NodeValue::I(bv)) => { Some(NodeValue::I(av.pow(*bv as u32))) },
-        (NodeValue::F(av), NodeValue::I(bv)) => { Some(NodeValue::F(av.powi(*bv as i32))) },
-        (_, _) => return Some(NodeValue::F(a.to_float(0.0).powf(b.to_float(1.0)))),
+fn sys_pow(a: NodeValue, b: NodeValue) -> NodeValue {
+    match NodeValue::calc_pow(&a, &b) {
+        NodeValue::Empty => NodeValue::F(a.to_float(0.0).powf(b.to_float(1.0))),
+        v => v,
     }
 }
-fn sys_pow2(_: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
-    let a = &args[0];
+fn sys_pow2(a: NodeValue) -> NodeValue {
     match a {
-        NodeValue::I(av) => { Some(NodeValue::I(av.pow(2))) },
-        NodeValue::F(av) => { Some(NodeValue::F(av.powi(2))) },
-        _ => return Some(NodeValue::F(a.to_float(0.0).powi(2))),
+        NodeValue::I(_) | NodeValue::BigInt(_) => NodeValue::calc_mul(&a, &a),
+        NodeValue::F(av) => NodeValue::F(av.powi(2)),
+        _ => NodeValue::F(a.to_float(0.0).powi(2)),
     }
 }
-fn sys_gt(_: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
-    let a = &args[0];
-    let b = &args[1];
-    let res = NodeValue::calc_gt(a, b);
-    Some(res)
+fn sys_gt(a: NodeValue, b: NodeValue) -> NodeValue {
+    NodeValue::calc_gt(&a, &b)
 }
-fn sys_gteq(_: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
-    let a = &args[0];
-    let b = &args[1];
-    let res = NodeValue::calc_gteq(a, b);
-    Some(res)
+fn sys_gteq(a: NodeValue, b: NodeValue) -> NodeValue {
+    NodeValue::calc_gteq(&a, &b)
 }
-fn sys_lt(_: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
-    let a = &args[0];
-    let b = &args[1];
-    let res = NodeValue::calc_lt(a, b);
-    Some(res)
+fn sys_lt(a: NodeValue, b: NodeValue) -> NodeValue {
+    NodeValue::calc_lt(&a, &b)
 }
-fn sys_lteq(_: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
-    let a = &args[0];
-    let b = &args[1];
-    let res = NodeValue::calc_lteq(a, b);
-    Some(res)
+fn sys_lteq(a: NodeValue, b: NodeValue) -> NodeValue {
+    NodeValue::calc_lteq(&a, &b)
 }
-fn sys_eq(_: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
-    let a = &args[0];
-    let b = &args[1];
-    let res = NodeValue::calc_eq(a, b);
-    Some(res)
+fn sys_eq(a: NodeValue, b: NodeValue) -> NodeValue {
+    NodeValue::calc_eq(&a, &b)
 }
-fn sys_typeof(_: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
-    let a = &args[0];
-    let s = match a {
+/// 一行入力する (数値として読めれば数値を返す)
+fn sys_input(ctx: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
+    let s = ctx.input(&args[0].to_string());
+    let t = s.trim();
+    if let Ok(v) = t.parse::<isize>() { return Some(NodeValue::I(v)); }
+    if t.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+        if let Ok(v) = t.parse::<f64>() { return Some(NodeValue::F(v)); }
+    }
+    Some(NodeValue::S(s))
+}
+fn sys_typeof(a: NodeValue) -> String {
+    let s = match &a {
         NodeValue::B(_) => { "B" },
-        NodeValue::I(_) => { "I" },
+        NodeValue::I(_) | NodeValue::BigInt(_) => { "I" },
         NodeValue::F(_) => { "F" },
+        NodeValue::Decimal(_) => { "D" },
         NodeValue::S(_) => { "S" },
+        NodeValue::H(_) => { "H" },
+        NodeValue::R(name, _) => { name },
+        NodeValue::Func(_) => { "関数" },
         _ => { "?" },
     };
-    Some(NodeValue::from_str(s))
+    String::from(s)
 }
-fn sys_toint(_: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
-    let a = &args[0];
-    Some(NodeValue::I(a.to_int(0)))
+fn sys_len(a: NodeValue) -> isize {
+    let n = match &a {
+        NodeValue::A(list) => list.len(),
+        NodeValue::H(dict) | NodeValue::R(_, dict) => dict.len(),
+        NodeValue::Empty => 0,
+        v => v.to_string().chars().count(),
+    };
+    n as isize
 }
-fn sys_tofloat(_: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
-    let a = &args[0];
-    Some(NodeValue::F(a.to_float(0.0)))
+fn sys_toint(a: NodeValue) -> NodeValue {
+    match &a {
+        NodeValue::BigInt(_) => a,
+        // isizeに収まらない整数の文字列は任意精度の整数にする
+        NodeValue::S(s) if s.trim().parse::<isize>().is_err() => match BigInt::parse(s.trim()) {
+            Some(v) => NodeValue::from_bigint(v),
+            None => NodeValue::I(a.to_int(0)),
+        },
+        _ => NodeValue::I(a.to_int(0)),
+    }
 }
-fn sys_hex(_: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
-    let a = &args[0];
-    Some(NodeValue::S(format!("{:X}", a.to_int(0))))
+fn sys_tofloat(a: NodeValue) -> f64 {
+    a.to_float(0.0)
 }
-fn sys_bin(_: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
+/// 10進数の小数に変換する (変換できなければ0)
+fn sys_todecimal(ctx: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
     let a = &args[0];
-    Some(NodeValue::S(format!("{:b}", a.to_int(0))))
+    let v = match a {
+        NodeValue::Decimal(v) => Some(*v),
+        NodeValue::I(v) => Some(Decimal::from_isize(*v)),
+        NodeValue::S(v) => Decimal::parse(v.trim()).or_else(|| Decimal::from_f64(a.to_float(0.0))),
+        _ => Decimal::from_f64(a.to_float(0.0)),
+    };
+    match v {
+        Some(v) => Some(NodeValue::Decimal(v)),
+        None => {
+            ctx.throw_sysfunc_error(format!("『{}』は10進数の小数に変換できません。", a.to_string()));
+            None
+        }
+    }
+}
+fn sys_hex(a: NodeValue) -> String {
+    match &a {
+        NodeValue::BigInt(v) => v.to_hex_string(),
+        _ => format!("{:X}", a.to_int(0)),
+    }
+}
+fn sys_bin(a: NodeValue) -> String {
+    match &a {
+        NodeValue::BigInt(v) => v.to_bin_string(),
+        _ => format!("{:b}", a.to_int(0)),
+    }
+}
+fn sys_replace(s: String, a: String, b: String) -> String {
+    s.replace(&a, &b)
+}
+fn sys_throw_error(ctx: &mut NodeContext, args: Vec<NodeValue>) -> Option<NodeValue> {
+    let msg = if !args.is_empty() { args[0].to_string() } else { String::from("エラーが発生しました") };
+    ctx.throw_sysfunc_error(msg);
+    None
 }
 
 #[cfg(test)]
@@ -185,6 +220,43 @@ mod test_runner {
         assert_eq!(res, "「");
     }
     #[test]
+    fn test_len() {
+        let res = eval_str("[1,2,3]の要素数を表示");
+        assert_eq!(res, "3");
+        let res = eval_str("{\"a\":1}の要素数を表示");
+        assert_eq!(res, "1");
+        let res = eval_str("「あいう」の要素数を表示");
+        assert_eq!(res, "3");
+    }
+    #[test]
+    fn test_bigint() {
+        let res = eval_str("2の100のべき乗のHEXを表示");
+        assert_eq!(res, format!("1{}", "0".repeat(25)));
+        let res = eval_str("2の65のべき乗の二進を表示");
+        assert_eq!(res, format!("1{}", "0".repeat(65)));
+        let res = eval_str("「123456789012345678901234567890」のINTを表示");
+        assert_eq!(res, "123456789012345678901234567890");
+        let res = eval_str("「123」のINTの変数型確認を表示");
+        assert_eq!(res, "I");
+        let res = eval_str("4294967296の二乗を表示");
+        assert_eq!(res, "18446744073709551616");
+        let res = eval_str("2の(0-1)のべき乗を表示");
+        assert_eq!(res, "0.5");
+    }
+    #[test]
+    fn test_decimal() {
+        let res = eval_str("A=「0.1」の小数;B=「0.2」の小数;A+Bを表示");
+        assert_eq!(res, "0.3");
+        let res = eval_str("A=「19.99」の小数;A*3を表示");
+        assert_eq!(res, "59.97");
+        let res = eval_str("0.1の小数の変数型確認を表示");
+        assert_eq!(res, "D");
+        let res = eval_str("「1.25」のDECIMALのINTを表示");
+        assert_eq!(res, "1");
+        let res = eval_str("「1.25」の小数のFLOATの変数型確認を表示");
+        assert_eq!(res, "F");
+    }
+    #[test]
     fn test_typeof() {
         let res = eval_str("「あ」の変数型確認して表示");
         assert_eq!(res, "S");
@@ -192,9 +264,24 @@ mod test_runner {
         assert_eq!(res, "I");
         let res = eval_str("3.0の変数型確認して表示");
         assert_eq!(res, "F");
+        let res = eval_str("{\"a\":1}の変数型確認して表示");
+        assert_eq!(res, "H");
         let res = eval_str("255のHEXを表示");
         assert_eq!(res, "FF");
         let res = eval_str("255の二進を表示");
         assert_eq!(res, "11111111");
     }
+    #[test]
+    fn test_replace() {
+        let res = eval_str("「abc」の「b」を「x」に置換して表示");
+        assert_eq!(res, "axc");
+        // 助詞で引数が決まるので順番を入れ替えてもよい
+        let res = eval_str("「b」を「abc」の「x」に置換して表示");
+        assert_eq!(res, "axc");
+        let res = eval_str("「x」へ「b」から「abc」で置換して表示");
+        assert_eq!(res, "axc");
+        // 引数の型が違えばエラー
+        let res = eval_str("[1,2]の「1」を「x」に置換して表示");
+        assert!(res.contains("1番目の引数には文字列が必要"), "{}", res);
+    }
 }
\ No newline at end of file
@@ -8,12 +8,16 @@ pub enum TokenKind {
     None,
     Comment,
     Comma,
+    Colon,
     Eol,
     Int,
     Number,
+    Decimal,
     String,
     Word,
     Flag,
+    /// 字句解析のエラー (値はエラーメッセージ)
+    Error,
     Eq,
     NotEq,
     Gt,
@@ -32,6 +36,11 @@ pub enum TokenKind {
     If,
     Else,
     Kai,
+    While,
+    Foreach,
+    Switch,
+    Try,
+    Catch,
     Plus,
     Minus,
     Mul,
@@ -39,6 +48,10 @@ pub enum TokenKind {
     Mod,
     Pow,
     PlusStr,
+    PlusEq,
+    MinusEq,
+    MulEq,
+    DivEq,
     And,
     Or,
     True,
@@ -49,7 +62,11 @@ pub enum TokenKind {
     DefFunc,
     Return,
     DefVar,
+    DefConst,
+    Func,
     Dainyu,
+    Inc,
+    Dec,
 }
 
 /// トークンのソースコード情報を表現する構造体
@@ -136,12 +153,15 @@ impl std::fmt::Display for Token {
             TokenKind::None => format!("None"),
             TokenKind::Comment => format!("Comment:{}", get_value(t)),
             TokenKind::Comma => format!(","),
+            TokenKind::Colon => String::from(":"),
             TokenKind::Eol => format!("Eol"),
             TokenKind::Int => format!("Int:{}", get_value(t)),
             TokenKind::Number => format!("Number:{}", get_value(t)),
+            TokenKind::Decimal => format!("Decimal:{}", get_value(t)),
             TokenKind::String => format!("String:{}", get_value(t)),
             TokenKind::Word => format!("Word:{}", get_value(t)),
             TokenKind::Flag => format!("Flag:{}", get_value(t)),
+            TokenKind::Error => format!("Error:{}", get_value(t)),
             TokenKind::ParenL => String::from("("),
             TokenKind::ParenR => String::from(")"),
             TokenKind::Eq => format!("="),
@@ -160,6 +180,11 @@ impl std::fmt::Display for Token {
             TokenKind::If => format!("もし"),
             TokenKind::Else => format!("違えば"),
             TokenKind::Kai => format!("Kai"),
+            TokenKind::While => String::from("間"),
+            TokenKind::Foreach => String::from("反復"),
+            TokenKind::Switch => String::from("条件分岐"),
+            TokenKind::Try => String::from("エラー監視"),
+            TokenKind::Catch => String::from("エラーならば"),
             TokenKind::BlockBegin => format!("ここから"),
             TokenKind::BlockEnd => format!("ここまで"),
             TokenKind::BracketL => String::from("["),
@@ -171,13 +196,21 @@ impl std::fmt::Display for Token {
             TokenKind::And => String::from("&&"),
             TokenKind::Or => String::from("||"),
             TokenKind::PlusStr => String::from("&"),
+            TokenKind::PlusEq => String::from("+="),
+            TokenKind::MinusEq => String::from("-="),
+            TokenKind::MulEq => String::from("*="),
+            TokenKind::DivEq => String::from("/="),
             TokenKind::Break => String::from("抜"),
             TokenKind::Continue => String::from("続"),
             TokenKind::For => String::from("繰返"),
             TokenKind::DefFunc => String::from("●関数"),
             TokenKind::Return => String::from("戻"),
             TokenKind::DefVar => String::from("変数"),
+            TokenKind::DefConst => String::from("定数"),
+            TokenKind::Func => String::from("関数"),
             TokenKind::Dainyu => String::from("代入"),
+            TokenKind::Inc => String::from("増"),
+            TokenKind::Dec => String::from("減"),
             // _ => format!("{:?}", self),
         };
         write!(f, "{}", s)
@@ -89,6 +89,12 @@ impl TokenCur {
         if index >= self.length { return; }
         self.tokens[index] = t;
     }
+    /// insert token
+    pub fn insert_token(&mut self, index: usize, t: Token) {
+        if index > self.length { return; }
+        self.tokens.insert(index, t);
+        self.length += 1;
+    }
 }
 
 #[cfg(test)]
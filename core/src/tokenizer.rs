@@ -1,6 +1,7 @@
 //! 字句解析器
 
 use crate::prepare;
+use crate::dncl;
 use crate::strcur::StrCur;
 use crate::kanautils;
 use crate::josi_list;
@@ -8,18 +9,24 @@ use crate::reserved_words;
 use crate::token::*;
 use crate::nvalue::NValue;
 use crate::tokencur::TokenCur;
+use crate::bigint::BigInt;
 
 #[derive(Debug, Clone)]
 pub struct Tokenizer {
     pub cur: StrCur,
+    /// 小数を10進数の小数として読む (『!小数モード』で有効になる)
+    pub decimal_mode: bool,
 }
 
 impl Tokenizer {
     /// 新しいインスタンスを生成する
     pub fn new(src: &str, start: i32, fileno: i32) -> Tokenizer {
-        let src = prepare::convert(src, fileno);
+        // DNCLモードであればなでしこの書き方に変換する
+        let src = if dncl::is_dncl(src) { dncl::convert(src) } else { String::from(src) };
+        let src = prepare::convert(&src, fileno);
         Tokenizer {
             cur: StrCur::from_source(&src, start, fileno),
+            decimal_mode: false,
         }
     }
     /// 文字列をトークンに区切る
@@ -73,6 +80,7 @@ impl Tokenizer {
                 },
                 '💧' => { flag_push(TokenKind::BlockEnd, &mut result, &mut cur); continue; }
                 ',' => { flag_push(TokenKind::Comma, &mut result, &mut cur); continue; },
+                ':' => { flag_push(TokenKind::Colon, &mut result, &mut cur); continue; },
                 '/' => { result.push(read_slash(&mut cur)); continue; },
                 '※' => { result.push(read_linecomment(&mut cur)); continue; },
                 '#' => { result.push(read_linecomment(&mut cur)); continue; },
@@ -89,9 +97,21 @@ impl Tokenizer {
                 '{' => { flag_push(TokenKind::CurBracketL, &mut result, &mut cur); continue; },
                 '}' => { flag_push_josi(TokenKind::CurBracketR, &mut result, &mut cur); continue; },
                 // 演算子
-                '+' => { flag_push(TokenKind::Plus, &mut result, &mut cur); continue; },
-                '-' => { flag_push(TokenKind::Minus, &mut result, &mut cur); continue; },
-                '*' => { flag_push(TokenKind::Mul, &mut result, &mut cur); continue; },
+                '+' => {
+                    if cur.eq_str("+=") { flag_push_n(TokenKind::PlusEq, '+', &mut result, &mut cur, 2); }
+                    else { flag_push(TokenKind::Plus, &mut result, &mut cur); }
+                    continue;
+                },
+                '-' => {
+                    if cur.eq_str("-=") { flag_push_n(TokenKind::MinusEq, '-', &mut result, &mut cur, 2); }
+                    else { flag_push(TokenKind::Minus, &mut result, &mut cur); }
+                    continue;
+                },
+                '*' => {
+                    if cur.eq_str("*=") { flag_push_n(TokenKind::MulEq, '*', &mut result, &mut cur, 2); }
+                    else { flag_push(TokenKind::Mul, &mut result, &mut cur); }
+                    continue;
+                },
                 '×' => { flag_push_n(TokenKind::Mul, '*', &mut result, &mut cur, 1); continue; },
                 '÷' => { flag_push_n(TokenKind::Div, '/', &mut result, &mut cur, 1); continue; },
                 '%' => { flag_push(TokenKind::Mod, &mut result, &mut cur); continue; },
@@ -120,7 +140,11 @@ impl Tokenizer {
                     continue; 
                 },
                 '!' => {
-                    if cur.eq_str("!=") { flag_push_n(TokenKind::NotEq, '≠', &mut result, &mut cur, 2); }
+                    if cur.eq_str("!小数モード") {
+                        self.decimal_mode = true;
+                        result.push(read_linecomment(&mut cur));
+                    }
+                    else if cur.eq_str("!=") { flag_push_n(TokenKind::NotEq, '≠', &mut result, &mut cur, 2); }
                     else { flag_push(TokenKind::Not, &mut result, &mut cur); }
                     continue; 
                 },
@@ -140,9 +164,17 @@ impl Tokenizer {
                 // '!'..='.' => { flag_push(TokenKind::Flag, &mut result, &mut cur); continue; },
                 // ':'..='@' => { flag_push(TokenKind::Flag, &mut result, &mut cur); continue; },
                 // 数値
-                '0'..='9' => { result.push(read_number(&mut cur)); continue; },
+                '0'..='9' => { result.push(read_number(&mut cur, self.decimal_mode)); continue; },
                 // word
                 'a'..='z' | 'A'..='Z' | '_' => { read_word(&mut result, &mut cur); continue; }
+                // 漢数字
+                '〇' | '一' | '二' | '三' | '四' | '五' | '六' | '七' | '八' | '九' | '十' | '百' | '千' => {
+                    match read_kanji_number(&mut cur) {
+                        Some(t) => result.push(t),
+                        None => { read_word(&mut result, &mut cur); },
+                    }
+                    continue;
+                },
                 n if n > (0xE0 as char) => { read_word(&mut result, &mut cur); continue; }
                 _ => {} // pass
             }
@@ -200,8 +232,9 @@ impl Tokenizer {
                         }
                         result.push(Token::new(TokenKind::ParenR, NValue::from_char(')'), None, end_pos));
                         result.push(Token::new(TokenKind::PlusStr, NValue::from_char('結'), None, end_pos));
-                        is_extract = false;
                     }
+                    is_extract = false;
+                    code = String::new();
                     continue;
                 }
                 code.push(c);
@@ -341,36 +374,204 @@ fn read_slash(cur: &mut StrCur) -> Token {
     // flag
     let start = cur.get_index_i();
     let flag = cur.next();
+    if cur.peek() == '=' { // "/="
+        cur.next();
+        let end = cur.get_index_i();
+        return Token::new_char(TokenKind::DivEq, flag, TokenPos::new(start, end, cur.fileno));
+    }
     let end = cur.get_index_i();
     return Token::new_char(TokenKind::Div, flag, TokenPos::new(start, end, cur.fileno));
 }
 
-fn read_number(cur: &mut StrCur) -> Token {
+fn read_number(cur: &mut StrCur, decimal_mode: bool) -> Token {
     let start = cur.get_index_i();
-    let mut vc: Vec<char> = vec![];
-    while cur.peek_in_range('0', '9') {
-        vc.push(cur.next());
+    // 16進数・8進数・2進数 (0xFF / 0o17 / 0b1010)
+    let radix = match cur.peek_str(2).as_str() {
+        "0x" | "0X" => 16,
+        "0o" | "0O" => 8,
+        "0b" | "0B" => 2,
+        _ => 10,
+    };
+    if radix != 10 && cur.peek_chars(3).get(2).is_some_and(|c| c.is_digit(radix)) {
+        cur.seek(2); // skip "0x"
+        let num_s = read_digits(cur, radix);
+        let josi_opt = josi_list::read_josi(cur);
+        let end = cur.get_index_i();
+        let nv = match i64::from_str_radix(&num_s, radix) {
+            Ok(v) => NValue::from_int(v),
+            Err(_) => NValue::from_string(BigInt::parse_radix(&num_s, radix).unwrap_or(BigInt::zero()).to_string()),
+        };
+        return Token::new(TokenKind::Int, nv, josi_opt, TokenPos::new(start, end, cur.fileno));
     }
+    // 『0x』の後に数字がない
+    if radix != 10 {
+        let mut num_s = String::new();
+        while cur.peek().is_ascii_alphanumeric() || cur.peek() == '_' { num_s.push(cur.next()); }
+        return number_error(cur, start, &num_s);
+    }
+    let mut num_s = read_digits(cur, 10);
+    let mut is_float = false;
     // float value
     if cur.peek() == '.' {
-        vc.push(cur.next());
-        while cur.peek_in_range('0', '9') {
-            vc.push(cur.next());
-        }
-        let num_s: String = vc.iter().collect();
+        num_s.push(cur.next());
+        num_s.push_str(&read_digits(cur, 10));
+        is_float = true;
+    }
+    // 指数表記 (1e-3 / 2.5E10)
+    let exp = cur.peek_chars(3);
+    let has_exp = match exp.as_slice() {
+        ['e' | 'E', '+' | '-', d, ..] | ['e' | 'E', d, ..] => d.is_ascii_digit(),
+        _ => false,
+    };
+    if has_exp {
+        num_s.push(cur.next());
+        if cur.peek() == '+' || cur.peek() == '-' { num_s.push(cur.next()); }
+        num_s.push_str(&read_digits(cur, 10));
         let josi_opt = josi_list::read_josi(cur);
         let end = cur.get_index_i();
+        let nv = NValue::from_float(num_s.parse().unwrap_or(0.0));
+        return Token::new(TokenKind::Number, nv, josi_opt, TokenPos::new(start, end, cur.fileno));
+    }
+    if is_float {
+        let josi_opt = josi_list::read_josi(cur);
+        let end = cur.get_index_i();
+        // 小数モードなら文字列のまま渡す (構文解析で10進数の小数にする)
+        if decimal_mode {
+            return Token::new(TokenKind::Decimal, NValue::from_string(num_s), josi_opt, TokenPos::new(start, end, cur.fileno));
+        }
         let nv = NValue::from_float(NValue::from_string(num_s).to_float_def(0.0));
         return Token::new(TokenKind::Number, nv, josi_opt, TokenPos::new(start, end, cur.fileno));
     }
+    // 「3万」や「1億2千万」のように漢数字の位が続く場合 (「3千葉」のように語句が続くなら数字だけ読む)
+    if is_kanji_unit(cur.peek()) {
+        let mut tmp = cur.clone();
+        let mut chars: Vec<char> = num_s.chars().collect();
+        read_kanji_chars(&mut tmp, &mut chars);
+        if is_kanji_number_end(&tmp) {
+            *cur = tmp;
+            match parse_kanji_number(&chars) {
+                Some(v) => num_s = v.to_string(),
+                None => return number_error(cur, start, &chars.iter().collect::<String>()),
+            }
+        }
+    }
     // int value
-    let num_s: String = vc.iter().collect();
     let josi_opt = josi_list::read_josi(cur);
     let end = cur.get_index_i();
-    let nv = NValue::from_int(NValue::from_string(num_s).to_int_def(0));
+    // i64に収まらない桁数の整数は文字列のまま渡す (構文解析で任意精度の整数にする)
+    let nv = match num_s.parse::<i64>() {
+        Ok(v) => NValue::from_int(v),
+        Err(_) => NValue::from_string(num_s),
+    };
     return Token::new(TokenKind::Int, nv, josi_opt, TokenPos::new(start, end, cur.fileno));
 }
 
+/// 数値の書き方が正しくない場合のエラー
+fn number_error(cur: &StrCur, start: i32, num_s: &str) -> Token {
+    let end = cur.get_index_i();
+    let msg = format!("数値『{}』の書き方が正しくありません。", num_s);
+    Token::new(TokenKind::Error, NValue::from_string(msg), None, TokenPos::new(start, end, cur.fileno))
+}
+
+/// radix進数の数字を読む (数字の間の「_」は区切りとして読み飛ばす)
+fn read_digits(cur: &mut StrCur, radix: u32) -> String {
+    let mut s = String::new();
+    loop {
+        let c = cur.peek();
+        if c.is_digit(radix) {
+            s.push(cur.next());
+            continue;
+        }
+        if c == '_' && !s.is_empty() && cur.peek_chars(2).get(1).is_some_and(|c| c.is_digit(radix)) {
+            cur.next(); // skip "_"
+            continue;
+        }
+        break;
+    }
+    s
+}
+
+/// 漢数字の数字であればその値を返す
+fn kanji_digit(c: char) -> Option<i128> {
+    "〇一二三四五六七八九".chars().position(|k| k == c).map(|n| n as i128)
+}
+
+/// 漢数字の位(十・百・千・万・億・兆)か
+fn is_kanji_unit(c: char) -> bool {
+    "十百千万億兆".contains(c)
+}
+
+/// 漢数字の数字と位を読み進める
+fn read_kanji_chars(cur: &mut StrCur, chars: &mut Vec<char>) {
+    while is_kanji_unit(cur.peek()) || kanji_digit(cur.peek()).is_some() || cur.peek().is_ascii_digit() {
+        chars.push(cur.next());
+    }
+}
+
+/// 漢数字の直後に語句が続いていないか (ただし「回」と助詞は続けて良い)
+fn is_kanji_number_end(cur: &StrCur) -> bool {
+    let c = cur.peek();
+    if kanautils::is_hiragana(c) {
+        let mut tmp = cur.clone();
+        let index = tmp.get_index();
+        josi_list::read_josi(&mut tmp);
+        return tmp.get_index() != index;
+    }
+    c == '回' || !kanautils::is_word_chars(c)
+}
+
+/// 「百二十三」や「1億2千万」のような漢数字を計算する (位の重複や順番の誤り、桁あふれはNone)
+fn parse_kanji_number(chars: &[char]) -> Option<i128> {
+    let mut total: i128 = 0; // 万・億・兆の位まで確定した値
+    let mut section: i128 = 0; // 万未満の値
+    let mut current: Option<i128> = None; // 位の前の数字
+    // 直前の位 (位は大きい順に一度ずつしか書けない)
+    let (mut last_small, mut last_large) = (i128::MAX, i128::MAX);
+    for c in chars {
+        if let Some(d) = kanji_digit(*c).or(c.to_digit(10).map(|d| d as i128)) {
+            current = Some(current.unwrap_or(0).checked_mul(10)?.checked_add(d)?);
+            continue;
+        }
+        let unit: i128 = match c {
+            '十' => 10, '百' => 100, '千' => 1000,
+            '万' => 10_000, '億' => 100_000_000, '兆' => 1_000_000_000_000,
+            _ => return None,
+        };
+        if unit < 10_000 {
+            if unit >= last_small { return None; }
+            last_small = unit;
+            section = section.checked_add(current.unwrap_or(1).checked_mul(unit)?)?;
+        } else {
+            if unit >= last_large { return None; }
+            (last_small, last_large) = (i128::MAX, unit);
+            let v = section.checked_add(current.unwrap_or(0))?;
+            total = total.checked_add(if v == 0 { 1 } else { v }.checked_mul(unit)?)?;
+            section = 0;
+        }
+        current = None;
+    }
+    total.checked_add(section)?.checked_add(current.unwrap_or(0))
+}
+
+/// 漢数字で始まる数値を読む (「一覧」や「二乗」のような語句の一部であればNone)
+fn read_kanji_number(cur: &mut StrCur) -> Option<Token> {
+    let start = cur.get_index_i();
+    let mut tmp = cur.clone();
+    let mut chars: Vec<char> = vec![];
+    read_kanji_chars(&mut tmp, &mut chars);
+    // 直後に語句が続くなら数値ではない
+    if !is_kanji_number_end(&tmp) { return None; }
+    let v = parse_kanji_number(&chars)?;
+    let josi_opt = josi_list::read_josi(&mut tmp);
+    *cur = tmp;
+    let end = cur.get_index_i();
+    let nv = match i64::try_from(v) {
+        Ok(v) => NValue::from_int(v),
+        Err(_) => NValue::from_string(v.to_string()),
+    };
+    Some(Token::new(TokenKind::Int, nv, josi_opt, TokenPos::new(start, end, cur.fileno)))
+}
+
 fn check_special(result: &mut Vec<Token>, cur: &mut StrCur, word: &str, kind: TokenKind, reg_word: &str) -> bool {
     let start = cur.get_index_i();
     if cur.eq_str(word) {
@@ -403,6 +604,7 @@ fn read_word(result: &mut Vec<Token>, cur: &mut StrCur) -> bool {
     if check_special(result, cur, "かつ", TokenKind::And, "&&") { return true; }
     if check_special(result, cur, "もしも", TokenKind::If, "もし") { return true; }
     if check_special(result, cur, "もし", TokenKind::If, "もし") { return true; }
+    if check_special(result, cur, "エラーならば", TokenKind::Catch, "エラーならば") { return true; }
 
     // ひらがなスタートなら1文字目は助詞にならない
     if kanautils::is_hiragana(cur.peek()) {
@@ -451,6 +653,13 @@ fn read_word(result: &mut Vec<Token>, cur: &mut StrCur) -> bool {
     true
 }
 
+/// 送りがなを削った語句を返す (関数名の照合にも使う)
+pub fn delete_okurigana_str(word: &str) -> String {
+    let word_v:Vec<char> = word.chars().collect();
+    let res_v = delete_okurigana(word_v);
+    res_v.iter().collect()
+}
+
 fn delete_okurigana(word: Vec<char>) -> Vec<char> {
     // 1文字なら送りがなはない
     if word.len() <= 1 {
@@ -460,11 +669,13 @@ fn delete_okurigana(word: Vec<char>) -> Vec<char> {
     // (ex) お兄さん → お兄 ... 漢字の後ろのひらがなのみ削る
     // (ex) うたう → うたう ... 全部ひらがなであれば削らない
     // (ex) INTする → INT ... アルファベットも漢字と見なす
+    // (ex) らーめん → らーめん ... ひらがなの後ろの長音記号はひらがなと見なす
     let mut result: Vec<char> = vec![];
     let mut is_hajime_hiragana = true;
     for c in word.iter() {
         // 漢字?
-        if !kanautils::is_hiragana(*c) {
+        let is_kana = kanautils::is_hiragana(*c) || (*c == 'ー' && is_hajime_hiragana);
+        if !is_kana {
             is_hajime_hiragana = false;
             result.push(*c);
             continue;
@@ -530,12 +741,6 @@ pub fn read_include_files(tokens: Vec<Token>) -> (Vec<Token>, Vec<String>) {
 #[cfg(test)]
 mod test_tokenizer {
     use super::*;
-
-    fn delete_okurigana_str(word: &str) -> String {
-        let word_v:Vec<char> = word.chars().collect();
-        let res_v = delete_okurigana(word_v);
-        res_v.iter().collect()
-    }
     
     #[test]
     fn test_tokenize() {
@@ -547,6 +752,8 @@ mod test_tokenizer {
         let t = tokenize_test("3\n3.14");
         assert_eq!(tokens_string(&t), "[Int:3][Eol][Number:3.14]");
         assert_eq!(tokens_string_pos(&t), "[Int:3](0,1)[Eol](1,2)[Number:3.14](2,6)");
+        let t = tokenize_test("!小数モード\n3.14");
+        assert_eq!(tokens_string(&t), "[Comment:小数モード][Decimal:3.14]");
         let t = tokenize_test("hoge=35");
         assert_eq!(tokens_string(&t), "[Word:hoge][=][Int:35]");
         assert_eq!(tokens_string_pos(&t), "[Word:hoge](0,4)[=](4,5)[Int:35](5,7)");
@@ -558,9 +765,51 @@ mod test_tokenizer {
         let t = tokenize_test("A=3*5");
         assert_eq!(tokens_string(&t), "[Word:A][=][Int:3][*][Int:5]");
         assert_eq!(tokens_string_pos(&t), "[Word:A](0,1)[=](1,2)[Int:3](2,3)[*](3,4)[Int:5](4,5)");
+        let t = tokenize_test("{\"a\":1}");
+        assert_eq!(tokens_string(&t), "[{][String:a][:][Int:1][}]");
         let t = tokenize_test("A = 3 * 5");
         assert_eq!(tokens_string(&t), "[Word:A][=][Int:3][*][Int:5]");
         assert_eq!(tokens_string_pos(&t), "[Word:A](0,1)[=](2,3)[Int:3](4,5)[*](6,7)[Int:5](8,9)");
+        let t = tokenize_test("A+=1;A-=2;A*=3;A/=4");
+        assert_eq!(tokens_string(&t), "[Word:A][+=][Int:1][Eol][Word:A][-=][Int:2][Eol][Word:A][*=][Int:3][Eol][Word:A][/=][Int:4]");
+        let t = tokenize_test("Aを1増やす");
+        assert_eq!(tokens_string(&t), "[Word:A/を][Int:1][増]");
+    }
+    #[test]
+    fn test_tokenize_number() {
+        let t = tokenize_test("0xFF;0b1010;0o17;0XffFF");
+        assert_eq!(tokens_string(&t), "[Int:255][Eol][Int:10][Eol][Int:15][Eol][Int:65535]");
+        let t = tokenize_test("1_000_000を表示");
+        assert_eq!(tokens_string(&t), "[Int:1000000/を][Word:表示]");
+        assert_eq!(tokens_string_pos(&t), "[Int:1000000/を](0,10)[Word:表示](10,12)");
+        let t = tokenize_test("1e-3;2.5E2;3e");
+        assert_eq!(tokens_string(&t), "[Number:0.001][Eol][Number:250][Eol][Int:3][Word:e]");
+        let t = tokenize_test("１２３＋０．５");
+        assert_eq!(tokens_string(&t), "[Int:123][+][Number:0.5]");
+        let t = tokenize_test("0x1_0000_0000_0000_0000");
+        assert_eq!(tokens_string(&t), "[Int:18446744073709551616]");
+        // 漢数字
+        let t = tokenize_test("百二十三を表示");
+        assert_eq!(tokens_string(&t), "[Int:123/を][Word:表示]");
+        assert_eq!(tokens_string_pos(&t), "[Int:123/を](0,5)[Word:表示](5,7)");
+        let t = tokenize_test("3万;1億2千万;二〇二四;千");
+        assert_eq!(tokens_string(&t), "[Int:30000][Eol][Int:120000000][Eol][Int:2024][Eol][Int:1000]");
+        let t = tokenize_test("三回");
+        assert_eq!(tokens_string(&t), "[Int:3][Kai]");
+        // 漢数字を含む語句はそのまま
+        let t = tokenize_test("一覧;二乗;一つ");
+        assert_eq!(tokens_string(&t), "[Word:一覧][Eol][Word:二乗][Eol][Word:一]");
+        // 位の重複や順番の誤り
+        let t = tokenize_test("百百;十百;万億");
+        assert_eq!(tokens_string(&t), "[Word:百百][Eol][Word:十百][Eol][Word:万億]");
+        let t = tokenize_test("3百百");
+        assert_eq!(tokens_string(&t), "[Error:数値『3百百』の書き方が正しくありません。]");
+        // 数字の後に位で始まる語句が続く
+        let t = tokenize_test("3千葉;2万円");
+        assert_eq!(tokens_string(&t), "[Int:3][Word:千葉][Eol][Int:2][Word:万円]");
+        // 『0x』の後に数字がない
+        let t = tokenize_test("0x_;0b");
+        assert_eq!(tokens_string(&t), "[Error:数値『0x_』の書き方が正しくありません。][Eol][Error:数値『0b』の書き方が正しくありません。]");
     }
     #[test]
     fn test_tokenize_josi() {
@@ -590,6 +839,19 @@ mod test_tokenizer {
         assert_eq!(delete_okurigana_str("F価格"), String::from("F価格"));
         assert_eq!(delete_okurigana_str("VS食べる"), String::from("VS食"));
         assert_eq!(delete_okurigana_str("INTする"), String::from("INT"));
+        // 活用しても同じ語句になる
+        assert_eq!(delete_okurigana_str("合計する"), String::from("合計"));
+        assert_eq!(delete_okurigana_str("合計し"), String::from("合計"));
+        assert_eq!(delete_okurigana_str("合計した"), String::from("合計"));
+        assert_eq!(delete_okurigana_str("書き出す"), String::from("書出"));
+        assert_eq!(delete_okurigana_str("書き出"), String::from("書出"));
+        // カタカナと長音
+        assert_eq!(delete_okurigana_str("ゲームする"), String::from("ゲーム"));
+        assert_eq!(delete_okurigana_str("らーめん"), String::from("らーめん"));
+        assert_eq!(delete_okurigana_str("すーぱーする"), String::from("すーぱーする"));
+        // 2回かけても変わらない
+        assert_eq!(delete_okurigana_str("置換"), String::from("置換"));
+        assert_eq!(delete_okurigana_str("お兄"), String::from("お兄"));
     }
 
     #[test]
@@ -598,6 +860,8 @@ mod test_tokenizer {
         assert_eq!(tokens_string(&t), "[Int:35][Kai]");
         let t = tokenize_test("N回");
         assert_eq!(tokens_string(&t), "[Word:N][Kai]");
+        let t = tokenize_test("エラー監視\nエラーならば");
+        assert_eq!(tokens_string(&t), "[エラー監視][Eol][エラーならば]");
     }
 
     #[test]
@@ -612,6 +876,8 @@ mod test_tokenizer {
     fn test_extract_string() {
         let t = tokenize_test("「a={a}」と表示");
         assert_eq!(tokens_string(&t), "[String:a=][&][(][Word:a][)][&][String:/と][Word:表示]");
+        let t = tokenize_test("「{a}:{b}」");
+        assert_eq!(tokens_string(&t), "[String:][&][(][Word:a][)][&][String::][&][(][Word:b][)][&][String:]");
     }
 
     #[test]